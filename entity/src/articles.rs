@@ -9,9 +9,77 @@ pub struct Model {
     pub id: i32,
     pub title: String,
     pub body: String,
+    pub category_id: i32,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub body_html: Option<String>,
+    pub created_at: DateTimeUtc,
+    pub updated_at: DateTimeUtc,
+    pub author_email: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
-pub enum Relation {}
+pub enum Relation {
+    #[sea_orm(has_many = "super::article_revisions::Entity")]
+    ArticleRevisions,
+    #[sea_orm(has_many = "super::article_tags::Entity")]
+    ArticleTags,
+    #[sea_orm(has_many = "super::attachments::Entity")]
+    Attachments,
+    #[sea_orm(
+        belongs_to = "super::categories::Entity",
+        from = "Column::CategoryId",
+        to = "super::categories::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Restrict"
+    )]
+    Categories,
+    #[sea_orm(has_many = "super::comment_subscriptions::Entity")]
+    CommentSubscriptions,
+}
+
+impl Related<super::article_revisions::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ArticleRevisions.def()
+    }
+}
+
+impl Related<super::article_tags::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ArticleTags.def()
+    }
+}
+
+impl Related<super::attachments::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Attachments.def()
+    }
+}
+
+impl Related<super::categories::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Categories.def()
+    }
+}
+
+impl Related<super::comment_subscriptions::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::CommentSubscriptions.def()
+    }
+}
 
 impl ActiveModelBehavior for ActiveModel {}
+
+pub struct ArticleToTag;
+
+impl Linked for ArticleToTag {
+    type FromEntity = Entity;
+
+    type ToEntity = super::tags::Entity;
+
+    fn link(&self) -> Vec<RelationDef> {
+        vec![
+            super::article_tags::Relation::Articles.def().rev(),
+            super::article_tags::Relation::Tags.def(),
+        ]
+    }
+}
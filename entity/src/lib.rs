@@ -2,5 +2,18 @@
 
 pub mod prelude;
 
+pub mod article_revisions;
+pub mod article_tags;
 pub mod articles;
+pub mod attachment_variants;
+pub mod attachments;
+pub mod blog_settings;
+pub mod categories;
+pub mod comment_subscriptions;
 pub mod comments;
+pub mod jobs;
+pub mod sea_orm_active_enums;
+pub mod tags;
+pub mod webhook_deliveries;
+pub mod webhook_delivery_attempts;
+pub mod webhooks;
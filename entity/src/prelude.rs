@@ -1,4 +1,16 @@
 //! `SeaORM` Entity. Generated by sea-orm-codegen 0.11.2
 
+pub use super::article_revisions::Entity as ArticleRevisions;
+pub use super::article_tags::Entity as ArticleTags;
 pub use super::articles::Entity as Articles;
+pub use super::attachment_variants::Entity as AttachmentVariants;
+pub use super::attachments::Entity as Attachments;
+pub use super::blog_settings::Entity as BlogSettings;
+pub use super::categories::Entity as Categories;
+pub use super::comment_subscriptions::Entity as CommentSubscriptions;
 pub use super::comments::Entity as Comments;
+pub use super::jobs::Entity as Jobs;
+pub use super::tags::Entity as Tags;
+pub use super::webhook_deliveries::Entity as WebhookDeliveries;
+pub use super::webhook_delivery_attempts::Entity as WebhookDeliveryAttempts;
+pub use super::webhooks::Entity as Webhooks;
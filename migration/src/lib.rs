@@ -2,6 +2,27 @@ pub use sea_orm_migration::prelude::*;
 
 mod m20230415_030812_create_articles;
 mod m20230419_061011_create_comments;
+mod m20230506_021344_create_tags;
+mod m20230506_021502_create_article_tags;
+mod m20230513_083120_create_categories;
+mod m20230513_083342_add_category_id_to_articles;
+mod m20230520_044517_add_body_html_to_articles;
+mod m20230527_012233_add_timestamps_to_articles_and_comments;
+mod m20230603_105916_create_article_revisions;
+mod m20230610_073408_add_parent_id_to_comments;
+mod m20230617_021755_add_moderation_status_to_comments;
+mod m20230617_022630_create_blog_settings;
+mod m20230624_031245_create_webhooks;
+mod m20230624_031530_create_webhook_deliveries;
+mod m20230624_031812_create_webhook_delivery_attempts;
+mod m20230701_024410_create_jobs;
+mod m20230708_020514_add_author_email_to_articles;
+mod m20230708_021136_create_comment_subscriptions;
+mod m20230715_013307_create_attachments;
+mod m20230722_014152_add_dimensions_to_attachments;
+mod m20230722_014420_create_attachment_variants;
+mod m20230729_020311_make_articles_category_id_not_null;
+mod m20230805_013512_add_moderated_at_to_comments;
 
 pub struct Migrator;
 
@@ -11,6 +32,27 @@ impl MigratorTrait for Migrator {
         vec![
             Box::new(m20230415_030812_create_articles::Migration),
             Box::new(m20230419_061011_create_comments::Migration),
+            Box::new(m20230506_021344_create_tags::Migration),
+            Box::new(m20230506_021502_create_article_tags::Migration),
+            Box::new(m20230513_083120_create_categories::Migration),
+            Box::new(m20230513_083342_add_category_id_to_articles::Migration),
+            Box::new(m20230520_044517_add_body_html_to_articles::Migration),
+            Box::new(m20230527_012233_add_timestamps_to_articles_and_comments::Migration),
+            Box::new(m20230603_105916_create_article_revisions::Migration),
+            Box::new(m20230610_073408_add_parent_id_to_comments::Migration),
+            Box::new(m20230617_021755_add_moderation_status_to_comments::Migration),
+            Box::new(m20230617_022630_create_blog_settings::Migration),
+            Box::new(m20230624_031245_create_webhooks::Migration),
+            Box::new(m20230624_031530_create_webhook_deliveries::Migration),
+            Box::new(m20230624_031812_create_webhook_delivery_attempts::Migration),
+            Box::new(m20230701_024410_create_jobs::Migration),
+            Box::new(m20230708_020514_add_author_email_to_articles::Migration),
+            Box::new(m20230708_021136_create_comment_subscriptions::Migration),
+            Box::new(m20230715_013307_create_attachments::Migration),
+            Box::new(m20230722_014152_add_dimensions_to_attachments::Migration),
+            Box::new(m20230722_014420_create_attachment_variants::Migration),
+            Box::new(m20230729_020311_make_articles_category_id_not_null::Migration),
+            Box::new(m20230805_013512_add_moderated_at_to_comments::Migration),
         ]
     }
 }
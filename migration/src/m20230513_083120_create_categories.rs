@@ -0,0 +1,48 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Categories::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Categories::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Categories::ParentId).integer().null())
+                    .col(ColumnDef::new(Categories::Name).string().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_categories_parent_id")
+                            .from(Categories::Table, Categories::ParentId)
+                            .to(Categories::Table, Categories::Id)
+                            .on_delete(ForeignKeyAction::Restrict),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Categories::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum Categories {
+    Table,
+    Id,
+    ParentId,
+    Name,
+}
@@ -0,0 +1,51 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Articles::Table)
+                    .add_column(ColumnDef::new(Articles::CategoryId).integer().null())
+                    .add_foreign_key(
+                        TableForeignKey::new()
+                            .name("fk_articles_category_id")
+                            .from_tbl(Articles::Table)
+                            .from_col(Articles::CategoryId)
+                            .to_tbl(Categories::Table)
+                            .to_col(Categories::Id)
+                            .on_delete(ForeignKeyAction::Restrict),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Articles::Table)
+                    .drop_foreign_key(Alias::new("fk_articles_category_id"))
+                    .drop_column(Articles::CategoryId)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum Articles {
+    Table,
+    CategoryId,
+}
+
+#[derive(Iden)]
+enum Categories {
+    Table,
+    Id,
+}
@@ -0,0 +1,80 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(ArticleRevisions::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(ArticleRevisions::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(ArticleRevisions::ArticleId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(ArticleRevisions::Revision)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(ArticleRevisions::Title).string().not_null())
+                    .col(ColumnDef::new(ArticleRevisions::Body).text().not_null())
+                    .col(
+                        ColumnDef::new(ArticleRevisions::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .index(
+                        Index::create()
+                            .name("idx_article_revisions_article_id_revision")
+                            .col(ArticleRevisions::ArticleId)
+                            .col(ArticleRevisions::Revision)
+                            .unique(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_article_revisions_article_id")
+                            .from(ArticleRevisions::Table, ArticleRevisions::ArticleId)
+                            .to(Articles::Table, Articles::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ArticleRevisions::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum ArticleRevisions {
+    Table,
+    Id,
+    ArticleId,
+    Revision,
+    Title,
+    Body,
+    CreatedAt,
+}
+
+#[derive(Iden)]
+enum Articles {
+    Table,
+    Id,
+}
@@ -0,0 +1,46 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Comments::Table)
+                    .add_column(ColumnDef::new(Comments::ParentId).integer().null())
+                    .add_foreign_key(
+                        TableForeignKey::new()
+                            .name("fk_comments_parent_id")
+                            .from_tbl(Comments::Table)
+                            .from_col(Comments::ParentId)
+                            .to_tbl(Comments::Table)
+                            .to_col(Comments::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Comments::Table)
+                    .drop_foreign_key(Alias::new("fk_comments_parent_id"))
+                    .drop_column(Comments::ParentId)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum Comments {
+    Table,
+    Id,
+    ParentId,
+}
@@ -0,0 +1,54 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(BlogSettings::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(BlogSettings::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(BlogSettings::CommentAutoApprove)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .exec_stmt(
+                Query::insert()
+                    .into_table(BlogSettings::Table)
+                    .columns([BlogSettings::Id, BlogSettings::CommentAutoApprove])
+                    .values_panic([1.into(), false.into()])
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(BlogSettings::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum BlogSettings {
+    Table,
+    Id,
+    CommentAutoApprove,
+}
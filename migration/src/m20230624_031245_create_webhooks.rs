@@ -0,0 +1,64 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Webhooks::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Webhooks::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Webhooks::Url).string_len(2048).not_null())
+                    .col(ColumnDef::new(Webhooks::Secret).string_len(64).not_null())
+                    .col(ColumnDef::new(Webhooks::Events).string().not_null())
+                    .col(
+                        ColumnDef::new(Webhooks::Active)
+                            .boolean()
+                            .not_null()
+                            .default(true),
+                    )
+                    .col(
+                        ColumnDef::new(Webhooks::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .col(
+                        ColumnDef::new(Webhooks::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Webhooks::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum Webhooks {
+    Table,
+    Id,
+    Url,
+    Secret,
+    Events,
+    Active,
+    CreatedAt,
+    UpdatedAt,
+}
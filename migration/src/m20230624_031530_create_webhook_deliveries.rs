@@ -0,0 +1,104 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(WebhookDeliveries::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(WebhookDeliveries::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(WebhookDeliveries::WebhookId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(WebhookDeliveries::Event)
+                            .string_len(32)
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(WebhookDeliveries::Payload).text().not_null())
+                    .col(
+                        ColumnDef::new(WebhookDeliveries::Status)
+                            .string_len(16)
+                            .not_null()
+                            .default("pending"),
+                    )
+                    .col(
+                        ColumnDef::new(WebhookDeliveries::Attempts)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(
+                        ColumnDef::new(WebhookDeliveries::NextAttemptAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(WebhookDeliveries::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .col(
+                        ColumnDef::new(WebhookDeliveries::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .index(
+                        Index::create()
+                            .name("idx_webhook_deliveries_status_next_attempt_at")
+                            .col(WebhookDeliveries::Status)
+                            .col(WebhookDeliveries::NextAttemptAt),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_webhook_deliveries_webhook_id")
+                            .from(WebhookDeliveries::Table, WebhookDeliveries::WebhookId)
+                            .to(Webhooks::Table, Webhooks::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(WebhookDeliveries::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum WebhookDeliveries {
+    Table,
+    Id,
+    WebhookId,
+    Event,
+    Payload,
+    Status,
+    Attempts,
+    NextAttemptAt,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(Iden)]
+enum Webhooks {
+    Table,
+    Id,
+}
@@ -0,0 +1,90 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(WebhookDeliveryAttempts::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(WebhookDeliveryAttempts::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(WebhookDeliveryAttempts::DeliveryId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(WebhookDeliveryAttempts::Attempt)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(WebhookDeliveryAttempts::ResponseStatus)
+                            .integer()
+                            .null(),
+                    )
+                    .col(ColumnDef::new(WebhookDeliveryAttempts::Error).text().null())
+                    .col(
+                        ColumnDef::new(WebhookDeliveryAttempts::DurationMs)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(WebhookDeliveryAttempts::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_webhook_delivery_attempts_delivery_id")
+                            .from(
+                                WebhookDeliveryAttempts::Table,
+                                WebhookDeliveryAttempts::DeliveryId,
+                            )
+                            .to(WebhookDeliveries::Table, WebhookDeliveries::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(WebhookDeliveryAttempts::Table)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum WebhookDeliveryAttempts {
+    Table,
+    Id,
+    DeliveryId,
+    Attempt,
+    ResponseStatus,
+    Error,
+    DurationMs,
+    CreatedAt,
+}
+
+#[derive(Iden)]
+enum WebhookDeliveries {
+    Table,
+    Id,
+}
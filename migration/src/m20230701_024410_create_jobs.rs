@@ -0,0 +1,92 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Jobs::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Jobs::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Jobs::Kind).string_len(64).not_null())
+                    .col(ColumnDef::new(Jobs::Payload).text().not_null())
+                    .col(
+                        ColumnDef::new(Jobs::Status)
+                            .string_len(16)
+                            .not_null()
+                            .default("pending"),
+                    )
+                    .col(
+                        ColumnDef::new(Jobs::Attempts)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(ColumnDef::new(Jobs::MaxAttempts).integer().not_null())
+                    .col(
+                        ColumnDef::new(Jobs::RunAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .col(
+                        ColumnDef::new(Jobs::LockedAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .col(ColumnDef::new(Jobs::LastError).text().null())
+                    .col(
+                        ColumnDef::new(Jobs::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .col(
+                        ColumnDef::new(Jobs::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .index(
+                        Index::create()
+                            .name("idx_jobs_status_run_at")
+                            .col(Jobs::Status)
+                            .col(Jobs::RunAt),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Jobs::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum Jobs {
+    Table,
+    Id,
+    Kind,
+    Payload,
+    Status,
+    Attempts,
+    MaxAttempts,
+    RunAt,
+    LockedAt,
+    LastError,
+    CreatedAt,
+    UpdatedAt,
+}
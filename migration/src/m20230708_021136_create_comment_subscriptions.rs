@@ -0,0 +1,89 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(CommentSubscriptions::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(CommentSubscriptions::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(CommentSubscriptions::ArticleId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(CommentSubscriptions::Email)
+                            .string_len(254)
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(CommentSubscriptions::UnsubscribedAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(CommentSubscriptions::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .col(
+                        ColumnDef::new(CommentSubscriptions::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .index(
+                        Index::create()
+                            .name("idx_comment_subscriptions_article_id_email")
+                            .col(CommentSubscriptions::ArticleId)
+                            .col(CommentSubscriptions::Email)
+                            .unique(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_comment_subscriptions_article_id")
+                            .from(CommentSubscriptions::Table, CommentSubscriptions::ArticleId)
+                            .to(Articles::Table, Articles::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(CommentSubscriptions::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum CommentSubscriptions {
+    Table,
+    Id,
+    ArticleId,
+    Email,
+    UnsubscribedAt,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(Iden)]
+enum Articles {
+    Table,
+    Id,
+}
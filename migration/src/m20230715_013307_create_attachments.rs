@@ -0,0 +1,76 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Attachments::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Attachments::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Attachments::ArticleId).integer().not_null())
+                    .col(
+                        ColumnDef::new(Attachments::StorageKey)
+                            .string()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(ColumnDef::new(Attachments::FileName).string().not_null())
+                    .col(
+                        ColumnDef::new(Attachments::ContentType)
+                            .string_len(127)
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(Attachments::Size).big_integer().not_null())
+                    .col(
+                        ColumnDef::new(Attachments::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_attachments_article_id")
+                            .from(Attachments::Table, Attachments::ArticleId)
+                            .to(Articles::Table, Articles::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Attachments::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum Attachments {
+    Table,
+    Id,
+    ArticleId,
+    StorageKey,
+    FileName,
+    ContentType,
+    Size,
+    CreatedAt,
+}
+
+#[derive(Iden)]
+enum Articles {
+    Table,
+    Id,
+}
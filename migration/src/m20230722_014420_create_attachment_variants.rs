@@ -0,0 +1,102 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(AttachmentVariants::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(AttachmentVariants::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(AttachmentVariants::AttachmentId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(AttachmentVariants::StorageKey)
+                            .string()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(
+                        ColumnDef::new(AttachmentVariants::ContentType)
+                            .string_len(127)
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(AttachmentVariants::Width)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(AttachmentVariants::Height)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(AttachmentVariants::Size)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(AttachmentVariants::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .index(
+                        Index::create()
+                            .name("idx_attachment_variants_attachment_id_content_type_width")
+                            .col(AttachmentVariants::AttachmentId)
+                            .col(AttachmentVariants::ContentType)
+                            .col(AttachmentVariants::Width)
+                            .unique(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_attachment_variants_attachment_id")
+                            .from(AttachmentVariants::Table, AttachmentVariants::AttachmentId)
+                            .to(Attachments::Table, Attachments::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(AttachmentVariants::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum AttachmentVariants {
+    Table,
+    Id,
+    AttachmentId,
+    StorageKey,
+    ContentType,
+    Width,
+    Height,
+    Size,
+    CreatedAt,
+}
+
+#[derive(Iden)]
+enum Attachments {
+    Table,
+    Id,
+}
@@ -0,0 +1,61 @@
+use sea_orm_migration::{prelude::*, sea_orm::ConnectionTrait};
+
+/// Articles written before categories existed are filed under this root
+/// category, which the server also falls back to for articles created without
+/// one.
+const DEFAULT_CATEGORY_NAME: &str = "Uncategorized";
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+
+        db.execute_unprepared(&format!(
+            "INSERT INTO `categories` (`parent_id`, `name`)
+             SELECT NULL, '{DEFAULT_CATEGORY_NAME}' FROM DUAL
+             WHERE EXISTS (SELECT 1 FROM `articles` WHERE `category_id` IS NULL)
+             AND NOT EXISTS (
+                 SELECT 1 FROM `categories`
+                 WHERE `parent_id` IS NULL AND `name` = '{DEFAULT_CATEGORY_NAME}'
+             )"
+        ))
+        .await?;
+        db.execute_unprepared(&format!(
+            "UPDATE `articles` SET `category_id` = (
+                 SELECT MIN(`id`) FROM `categories`
+                 WHERE `parent_id` IS NULL AND `name` = '{DEFAULT_CATEGORY_NAME}'
+             )
+             WHERE `category_id` IS NULL"
+        ))
+        .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Articles::Table)
+                    .modify_column(ColumnDef::new(Articles::CategoryId).integer().not_null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Articles::Table)
+                    .modify_column(ColumnDef::new(Articles::CategoryId).integer().null())
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(Iden)]
+enum Articles {
+    Table,
+    CategoryId,
+}
@@ -0,0 +1,320 @@
+use std::{collections::HashSet, env, sync::Arc};
+
+use actix_web::web;
+use mime::Mime;
+use rand::RngCore;
+use sea_orm::DatabaseConnection;
+
+use crate::{
+    imaging, jobs, repository,
+    storage::{LocalStorage, Storage},
+};
+
+pub const DEFAULT_MAX_SIZE: usize = 10 * 1024 * 1024;
+
+/// No HTML, SVG or anything else a browser would run scripts from, as
+/// attachments are served from the blog's own origin.
+pub const DEFAULT_ALLOWED_TYPES: &str =
+    "image/png,image/jpeg,image/gif,image/webp,application/pdf,text/plain";
+
+pub const DEFAULT_DIR: &str = "attachments";
+
+/// Files accepted in a single upload request.
+pub const MAX_FILES_PER_REQUEST: usize = 10;
+
+pub fn attachment_path(id: i32) -> String {
+    format!("/attachments/{id}")
+}
+
+pub fn variant_path(attachment_id: i32, id: i32) -> String {
+    format!("/attachments/{attachment_id}/variants/{id}")
+}
+
+/// Uploaded files, the limits they are held to, and the resized variants made
+/// of images.
+#[derive(Debug, Clone)]
+pub struct Attachments {
+    database_connection: DatabaseConnection,
+    jobs: jobs::JobQueue,
+    storage: Arc<dyn Storage>,
+    max_size: usize,
+    allowed_types: Vec<Mime>,
+    variant_widths: Vec<u32>,
+}
+
+impl Attachments {
+    /// No variants are made when `variant_widths` is empty.
+    pub fn new(
+        database_connection: DatabaseConnection,
+        jobs: jobs::JobQueue,
+        storage: Arc<dyn Storage>,
+        max_size: usize,
+        allowed_types: Vec<Mime>,
+        variant_widths: Vec<u32>,
+    ) -> Self {
+        Self {
+            database_connection,
+            jobs,
+            storage,
+            max_size,
+            allowed_types,
+            variant_widths,
+        }
+    }
+
+    /// Stores files in `ATTACHMENTS_DIR`, and reads the limits from
+    /// `ATTACHMENTS_MAX_SIZE`, in bytes, and `ATTACHMENTS_ALLOWED_TYPES`, a comma
+    /// separated list of MIME types. Images are resized to each of
+    /// `ATTACHMENTS_VARIANT_WIDTHS`, a comma separated list of widths in pixels,
+    /// which turns variants off when set but empty.
+    pub fn from_env(database_connection: DatabaseConnection, jobs: jobs::JobQueue) -> Self {
+        let dir = env::var("ATTACHMENTS_DIR").unwrap_or_else(|_| DEFAULT_DIR.to_string());
+        let max_size = env::var("ATTACHMENTS_MAX_SIZE")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(DEFAULT_MAX_SIZE);
+        let allowed_types = env::var("ATTACHMENTS_ALLOWED_TYPES")
+            .ok()
+            .filter(|value| !value.trim().is_empty())
+            .unwrap_or_else(|| DEFAULT_ALLOWED_TYPES.to_string())
+            .split(',')
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(|value| {
+                value
+                    .parse()
+                    .expect("ATTACHMENTS_ALLOWED_TYPES must be a list of MIME types")
+            })
+            .collect();
+        let mut variant_widths = env::var("ATTACHMENTS_VARIANT_WIDTHS")
+            .unwrap_or_else(|_| imaging::DEFAULT_VARIANT_WIDTHS.to_string())
+            .split(',')
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(|value| {
+                value
+                    .parse()
+                    .ok()
+                    .filter(|&width| width > 0)
+                    .expect("ATTACHMENTS_VARIANT_WIDTHS must be a list of widths in pixels")
+            })
+            .collect::<Vec<u32>>();
+        variant_widths.sort_unstable();
+        variant_widths.dedup();
+
+        Self::new(
+            database_connection,
+            jobs,
+            Arc::new(LocalStorage::new(dir)),
+            max_size,
+            allowed_types,
+            variant_widths,
+        )
+    }
+
+    pub fn storage(&self) -> &dyn Storage {
+        self.storage.as_ref()
+    }
+
+    /// Largest file accepted, in bytes.
+    pub fn max_size(&self) -> usize {
+        self.max_size
+    }
+
+    /// Whether files of `content_type` may be uploaded, whatever its
+    /// parameters.
+    pub fn allows(&self, content_type: &Mime) -> bool {
+        self.allowed_types
+            .iter()
+            .any(|allowed| allowed.essence_str() == content_type.essence_str())
+    }
+
+    /// Queues the variants of a newly stored attachment, if it is an image
+    /// they can be made of.
+    pub fn created(&self, attachment: &entity::attachments::Model) {
+        if self.variant_widths.is_empty() || !is_processable(&attachment.content_type) {
+            return;
+        }
+
+        // Queued in the background, like webhooks, so that the upload
+        // succeeds whether or not its variants can be made.
+        let jobs = self.jobs.clone();
+        let attachment_id = attachment.id;
+        actix_web::rt::spawn(async move {
+            if let Err(err) = jobs
+                .enqueue(jobs::Job::GenerateImageVariants { attachment_id })
+                .await
+            {
+                tracing::error!(error = %err, attachment_id, "failed to queue image variants");
+            }
+        });
+    }
+
+    /// Removes the stored file and variants of a deleted attachment, in the
+    /// background.
+    pub fn deleted(&self, attachment: &entity::attachments::Model) {
+        let storage = self.storage.clone();
+        let storage_key = attachment.storage_key.clone();
+        actix_web::rt::spawn(async move {
+            if let Err(err) = storage.delete(&storage_key).await {
+                tracing::error!(error = %err, storage = storage.name(), %storage_key, "failed to remove attachment file");
+            }
+            if let Err(err) = storage.delete_prefix(variants_prefix(&storage_key)).await {
+                tracing::error!(error = %err, storage = storage.name(), %storage_key, "failed to remove attachment variants");
+            }
+        });
+    }
+
+    /// Removes the stored files of a deleted article, whose attachments went
+    /// with it, in the background.
+    pub fn article_deleted(&self, article_id: i32) {
+        let storage = self.storage.clone();
+        actix_web::rt::spawn(async move {
+            if let Err(err) = storage.delete_prefix(&article_id.to_string()).await {
+                tracing::error!(error = %err, storage = storage.name(), article_id, "failed to remove attachment files");
+            }
+        });
+    }
+
+    /// Makes the variants of attachment `attachment_id` at each configured
+    /// width narrower than the image, and replaces any made before. Variant
+    /// keys only depend on the original and the width, so running this again
+    /// overwrites the same files.
+    pub async fn generate_variants(&self, attachment_id: i32) -> anyhow::Result<()> {
+        let attachments_repository =
+            repository::AttachmentsRepository::new(self.database_connection.clone());
+        let attachment_variants_repository =
+            repository::AttachmentVariantsRepository::new(self.database_connection.clone());
+
+        // Deleted since it was uploaded.
+        let attachment = match attachments_repository.find_by_id(attachment_id).await? {
+            Some(attachment) => attachment,
+            None => return Ok(()),
+        };
+        let content_type = attachment.content_type.parse::<Mime>()?;
+        if !imaging::is_processable(&content_type) {
+            return Ok(());
+        }
+
+        let data = self.storage.get(&attachment.storage_key).await?;
+        let widths = self.variant_widths.clone();
+        let variants =
+            web::block(move || imaging::variants(&data, &content_type, &widths)).await??;
+
+        let mut forms = Vec::with_capacity(variants.len());
+        for variant in variants {
+            let storage_key = variant_storage_key(
+                &attachment.storage_key,
+                variant.width,
+                &variant.content_type,
+            );
+            let size = variant.data.len() as i64;
+            self.storage.put(&storage_key, variant.data.into()).await?;
+
+            forms.push(entity::attachment_variants::Model {
+                id: 0,
+                attachment_id,
+                storage_key,
+                content_type: variant.content_type.essence_str().to_string(),
+                width: variant.width as i32,
+                height: variant.height as i32,
+                size,
+                created_at: chrono::Utc::now(),
+            });
+        }
+
+        let previous = attachment_variants_repository
+            .find_by_attachment_id(attachment_id)
+            .await?;
+        let variants = attachment_variants_repository
+            .replace(attachment_id, forms)
+            .await?;
+        tracing::debug!(
+            attachment_id,
+            variants = variants.len(),
+            "generated image variants"
+        );
+
+        // Files of widths that are no longer configured.
+        let kept = variants
+            .iter()
+            .map(|variant| variant.storage_key.as_str())
+            .collect::<HashSet<&str>>();
+        for variant in previous {
+            if !kept.contains(variant.storage_key.as_str()) {
+                self.storage.delete(&variant.storage_key).await?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn is_processable(content_type: &str) -> bool {
+    content_type
+        .parse::<Mime>()
+        .map(|content_type| imaging::is_processable(&content_type))
+        .unwrap_or(false)
+}
+
+fn extension(content_type: &Mime) -> &str {
+    match content_type.subtype().as_str() {
+        "plain" => "txt",
+        subtype if subtype.chars().all(|c| c.is_ascii_alphanumeric()) => subtype,
+        _ => "bin",
+    }
+}
+
+/// New random key for a file attached to article `article_id`. Keys are
+/// prefixed with the article ID, so that [`Attachments::article_deleted`] can
+/// find them.
+pub fn storage_key(article_id: i32, content_type: &Mime) -> String {
+    let mut id = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut id);
+
+    format!(
+        "{article_id}/{}.{}",
+        hex::encode(id),
+        extension(content_type)
+    )
+}
+
+/// Prefix of the variants of the file under `storage_key`: the key without
+/// its extension, so `12/3f2a....png` has its variants under `12/3f2a.../`.
+fn variants_prefix(storage_key: &str) -> &str {
+    storage_key
+        .rsplit_once('.')
+        .map_or(storage_key, |(stem, _)| stem)
+}
+
+/// Key of the `width` pixels wide variant of the file under `storage_key`.
+fn variant_storage_key(storage_key: &str, width: u32, content_type: &Mime) -> String {
+    format!(
+        "{}/{width}.{}",
+        variants_prefix(storage_key),
+        extension(content_type)
+    )
+}
+
+/// The last path segment of an uploaded file name, without control
+/// characters and at most 255 bytes long.
+pub fn sanitize_file_name(file_name: &str) -> String {
+    let file_name = file_name
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .filter(|c| !c.is_control())
+        .collect::<String>();
+    let file_name = file_name.trim();
+
+    let mut end = file_name.len().min(255);
+    while !file_name.is_char_boundary(end) {
+        end -= 1;
+    }
+
+    match &file_name[..end] {
+        "" | "." | ".." => "file".to_string(),
+        file_name => file_name.to_string(),
+    }
+}
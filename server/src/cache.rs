@@ -0,0 +1,250 @@
+use std::{
+    env,
+    future::Future,
+    num::NonZeroUsize,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use lru::LruCache;
+use sea_orm::DbErr;
+
+use crate::metrics;
+
+pub const DEFAULT_CAPACITY: usize = 1000;
+
+pub const DEFAULT_TTL: Duration = Duration::from_secs(60);
+
+type ArticleWithTags = (entity::articles::Model, Vec<entity::tags::Model>);
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Key {
+    Article(i32),
+    Tags(i32),
+    List,
+    ListByTagName(String),
+}
+
+impl Key {
+    fn is_list(&self) -> bool {
+        matches!(self, Self::List | Self::ListByTagName(_))
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Value {
+    Article(Option<entity::articles::Model>),
+    Tags(Vec<entity::tags::Model>),
+    List(Vec<ArticleWithTags>),
+}
+
+#[derive(Debug)]
+struct Entry {
+    value: Value,
+    expires_at: Instant,
+}
+
+#[derive(Debug)]
+struct State {
+    entries: LruCache<Key, Entry>,
+    /// Bumped by every invalidation, so that a read which started before a
+    /// write does not put what it loaded back in the cache afterwards.
+    generation: u64,
+}
+
+#[derive(Debug)]
+struct Inner {
+    state: Mutex<State>,
+    ttl: Duration,
+    metrics: Arc<metrics::Metrics>,
+}
+
+/// Read-through cache of the article reads behind `GET /articles` and
+/// `GET /articles/{id}`, shared by the [`crate::repository::ArticlesRepository`]
+/// and [`crate::repository::TagsRepository`] built with it. Entries are evicted
+/// least recently used first and expire after a TTL; writes made through those
+/// repositories drop exactly the entries they change. A disabled cache loads
+/// everything from the database.
+#[derive(Debug, Clone)]
+pub struct ArticleCache {
+    inner: Option<Arc<Inner>>,
+}
+
+impl ArticleCache {
+    pub fn new(capacity: NonZeroUsize, ttl: Duration, metrics: Arc<metrics::Metrics>) -> Self {
+        Self {
+            inner: Some(Arc::new(Inner {
+                state: Mutex::new(State {
+                    entries: LruCache::new(capacity),
+                    generation: 0,
+                }),
+                ttl,
+                metrics,
+            })),
+        }
+    }
+
+    pub fn disabled() -> Self {
+        Self { inner: None }
+    }
+
+    /// Turned off by setting `ARTICLE_CACHE_ENABLED` to `false`. Holds up to
+    /// `ARTICLE_CACHE_CAPACITY` entries for `ARTICLE_CACHE_TTL_SECONDS` each.
+    pub fn from_env(metrics: Arc<metrics::Metrics>) -> Self {
+        let enabled = env::var("ARTICLE_CACHE_ENABLED")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(true);
+        if !enabled {
+            return Self::disabled();
+        }
+
+        let capacity = env::var("ARTICLE_CACHE_CAPACITY")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .and_then(NonZeroUsize::new)
+            .unwrap_or(NonZeroUsize::new(DEFAULT_CAPACITY).unwrap());
+        let ttl = env::var("ARTICLE_CACHE_TTL_SECONDS")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_TTL);
+
+        Self::new(capacity, ttl, metrics)
+    }
+
+    pub async fn article<F>(
+        &self,
+        id: i32,
+        load: F,
+    ) -> Result<Option<entity::articles::Model>, DbErr>
+    where
+        F: Future<Output = Result<Option<entity::articles::Model>, DbErr>>,
+    {
+        self.get_or_load(
+            Key::Article(id),
+            load,
+            Value::Article,
+            |value| match value {
+                Value::Article(article) => Some(article.clone()),
+                _ => None,
+            },
+        )
+        .await
+    }
+
+    pub async fn tags<F>(&self, article_id: i32, load: F) -> Result<Vec<entity::tags::Model>, DbErr>
+    where
+        F: Future<Output = Result<Vec<entity::tags::Model>, DbErr>>,
+    {
+        self.get_or_load(
+            Key::Tags(article_id),
+            load,
+            Value::Tags,
+            |value| match value {
+                Value::Tags(tags) => Some(tags.clone()),
+                _ => None,
+            },
+        )
+        .await
+    }
+
+    /// Every article with its tags, or only those tagged `tag_name`.
+    pub async fn list<F>(
+        &self,
+        tag_name: Option<&str>,
+        load: F,
+    ) -> Result<Vec<ArticleWithTags>, DbErr>
+    where
+        F: Future<Output = Result<Vec<ArticleWithTags>, DbErr>>,
+    {
+        let key = match tag_name {
+            Some(tag_name) => Key::ListByTagName(tag_name.to_string()),
+            None => Key::List,
+        };
+
+        self.get_or_load(key, load, Value::List, |value| match value {
+            Value::List(articles) => Some(articles.clone()),
+            _ => None,
+        })
+        .await
+    }
+
+    /// Drops what article `id` changes: the article itself, its tags, and
+    /// every list, which all could include it.
+    pub fn article_changed(&self, id: i32) {
+        let inner = match &self.inner {
+            Some(inner) => inner,
+            None => return,
+        };
+        let mut state = inner.state.lock().unwrap();
+
+        state.generation += 1;
+        state.entries.pop(&Key::Article(id));
+        state.entries.pop(&Key::Tags(id));
+
+        let lists = state
+            .entries
+            .iter()
+            .map(|(key, _)| key)
+            .filter(|key| key.is_list())
+            .cloned()
+            .collect::<Vec<Key>>();
+        for key in lists {
+            state.entries.pop(&key);
+        }
+    }
+
+    async fn get_or_load<T, F>(
+        &self,
+        key: Key,
+        load: F,
+        wrap: fn(T) -> Value,
+        unwrap: fn(&Value) -> Option<T>,
+    ) -> Result<T, DbErr>
+    where
+        T: Clone,
+        F: Future<Output = Result<T, DbErr>>,
+    {
+        let inner = match &self.inner {
+            Some(inner) => inner,
+            None => return load.await,
+        };
+
+        let generation = {
+            let mut state = inner.state.lock().unwrap();
+            let now = Instant::now();
+
+            match state.entries.get(&key) {
+                Some(entry) if entry.expires_at > now => {
+                    if let Some(value) = unwrap(&entry.value) {
+                        inner.metrics.article_cache_hit();
+                        return Ok(value);
+                    }
+                }
+                Some(_) => {
+                    state.entries.pop(&key);
+                }
+                None => {}
+            }
+
+            state.generation
+        };
+        inner.metrics.article_cache_miss();
+
+        let value = load.await?;
+
+        let mut state = inner.state.lock().unwrap();
+        if state.generation == generation {
+            state.entries.put(
+                key,
+                Entry {
+                    value: wrap(value.clone()),
+                    expires_at: Instant::now() + inner.ttl,
+                },
+            );
+        }
+
+        Ok(value)
+    }
+}
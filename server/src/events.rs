@@ -0,0 +1,318 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    convert::Infallible,
+    env,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use actix_web::{
+    get,
+    http::header::{CACHE_CONTROL, CONTENT_TYPE},
+    web::{self, Bytes},
+    HttpRequest, HttpResponse,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use crate::handler::AppError;
+
+pub const DEFAULT_BACKLOG: usize = 1000;
+
+/// New events a subscriber can fall behind by before it has to catch up from
+/// the backlog.
+const CHANNEL_CAPACITY: usize = 256;
+
+/// Comment lines sent while idle, so that proxies keep the connection open.
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// `published` is when an article goes live. Articles have no draft state, so
+/// that is as soon as they are created, right after `created`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ArticleEventKind {
+    Created,
+    Updated,
+    Deleted,
+    Published,
+}
+
+impl ArticleEventKind {
+    const ALL: [Self; 4] = [Self::Created, Self::Updated, Self::Deleted, Self::Published];
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Created => "created",
+            Self::Updated => "updated",
+            Self::Deleted => "deleted",
+            Self::Published => "published",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.as_str() == value)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ArticleEvent {
+    #[serde(skip)]
+    id: u64,
+    #[serde(skip)]
+    kind: ArticleEventKind,
+    article_id: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    title: Option<String>,
+    occurred_at: DateTime<Utc>,
+}
+
+impl ArticleEvent {
+    fn to_sse(&self) -> Bytes {
+        let data = serde_json::to_string(self).unwrap_or_default();
+
+        Bytes::from(format!(
+            "id: {}\nevent: {}\ndata: {}\n\n",
+            self.id,
+            self.kind.as_str(),
+            data
+        ))
+    }
+}
+
+/// Tells the client that events it asked for are gone, and where the stream
+/// resumes from. It should reload whatever state it keeps.
+fn reset_sse(last_event_id: u64) -> Bytes {
+    Bytes::from(format!("id: {last_event_id}\nevent: reset\ndata: {{}}\n\n"))
+}
+
+enum Resume {
+    Replay(Vec<ArticleEvent>),
+    Reset { last_event_id: u64 },
+}
+
+#[derive(Debug)]
+struct Backlog {
+    last_event_id: u64,
+    events: VecDeque<ArticleEvent>,
+    capacity: usize,
+}
+
+impl Backlog {
+    fn resume(&self, last_event_id: u64) -> Resume {
+        let oldest = self
+            .events
+            .front()
+            .map(|event| event.id)
+            .unwrap_or(self.last_event_id + 1);
+
+        // Ahead of us means the ID comes from an earlier process.
+        if last_event_id > self.last_event_id || last_event_id + 1 < oldest {
+            return Resume::Reset {
+                last_event_id: self.last_event_id,
+            };
+        }
+
+        Resume::Replay(
+            self.events
+                .iter()
+                .filter(|event| event.id > last_event_id)
+                .cloned()
+                .collect(),
+        )
+    }
+}
+
+/// Broadcasts article changes to `GET /events` subscribers, keeping the latest
+/// events so that reconnecting clients can resume with `Last-Event-ID`.
+#[derive(Debug, Clone)]
+pub struct ArticleEventHub {
+    backlog: Arc<Mutex<Backlog>>,
+    sender: broadcast::Sender<ArticleEvent>,
+}
+
+impl ArticleEventHub {
+    pub fn new(backlog_capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(CHANNEL_CAPACITY);
+
+        Self {
+            backlog: Arc::new(Mutex::new(Backlog {
+                // Starting from the clock keeps IDs increasing across restarts,
+                // so stale IDs from a previous process are recognised as such.
+                last_event_id: Utc::now().timestamp_millis().max(0) as u64,
+                events: VecDeque::with_capacity(backlog_capacity),
+                capacity: backlog_capacity,
+            })),
+            sender,
+        }
+    }
+
+    /// Reads the backlog size from `EVENTS_BACKLOG`.
+    pub fn from_env() -> Self {
+        let backlog_capacity = env::var("EVENTS_BACKLOG")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(DEFAULT_BACKLOG);
+
+        Self::new(backlog_capacity)
+    }
+
+    /// Publishes both `created` and `published`, as new articles are live
+    /// straight away.
+    pub fn created(&self, article: &entity::articles::Model) {
+        self.publish(
+            ArticleEventKind::Created,
+            article.id,
+            Some(article.title.clone()),
+        );
+        self.publish(
+            ArticleEventKind::Published,
+            article.id,
+            Some(article.title.clone()),
+        );
+    }
+
+    pub fn updated(&self, article: &entity::articles::Model) {
+        self.publish(
+            ArticleEventKind::Updated,
+            article.id,
+            Some(article.title.clone()),
+        );
+    }
+
+    pub fn deleted(&self, article_id: i32) {
+        self.publish(ArticleEventKind::Deleted, article_id, None);
+    }
+
+    fn publish(&self, kind: ArticleEventKind, article_id: i32, title: Option<String>) {
+        let mut backlog = self.backlog.lock().unwrap();
+
+        backlog.last_event_id += 1;
+        let event = ArticleEvent {
+            id: backlog.last_event_id,
+            kind,
+            article_id,
+            title,
+            occurred_at: Utc::now(),
+        };
+
+        if backlog.capacity > 0 {
+            if backlog.events.len() == backlog.capacity {
+                backlog.events.pop_front();
+            }
+            backlog.events.push_back(event.clone());
+        }
+
+        // Sent under the lock, so that subscribers joining concurrently see each
+        // event either in their replay or on their receiver, never both or neither.
+        // Sending only fails when nobody is listening.
+        let _ = self.sender.send(event);
+    }
+
+    fn subscribe(&self, last_event_id: Option<u64>) -> (Resume, broadcast::Receiver<ArticleEvent>) {
+        let backlog = self.backlog.lock().unwrap();
+
+        let resume = match last_event_id {
+            Some(last_event_id) => backlog.resume(last_event_id),
+            None => Resume::Replay(Vec::new()),
+        };
+
+        (resume, self.sender.subscribe())
+    }
+
+    fn resume(&self, last_event_id: u64) -> Resume {
+        self.backlog.lock().unwrap().resume(last_event_id)
+    }
+}
+
+#[derive(Deserialize)]
+struct EventsQuery {
+    /// Comma separated event types to receive, all of them by default.
+    types: Option<String>,
+}
+
+#[get("/events")]
+async fn events_index(
+    request: HttpRequest,
+    data: web::Data<super::AppState>,
+    query: web::Query<EventsQuery>,
+) -> Result<HttpResponse, AppError> {
+    let kinds: HashSet<ArticleEventKind> = match query.into_inner().types {
+        Some(types) => {
+            let mut kinds = HashSet::new();
+            for value in types
+                .split(',')
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+            {
+                match ArticleEventKind::parse(value) {
+                    Some(kind) => kinds.insert(kind),
+                    None => {
+                        return Err(AppError::bad_request(&format!(
+                            "unknown event type: {value}"
+                        )))
+                    }
+                };
+            }
+            kinds
+        }
+        None => ArticleEventKind::ALL.into_iter().collect(),
+    };
+
+    let last_event_id = request
+        .headers()
+        .get("Last-Event-ID")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse::<u64>().ok());
+
+    let hub = data.article_events.clone();
+    let (resume, mut receiver) = hub.subscribe(last_event_id);
+
+    let stream = async_stream::stream! {
+        let mut last_sent = last_event_id.unwrap_or(0);
+        let mut resume = Some(resume);
+        let mut keep_alive = actix_web::rt::time::interval(KEEP_ALIVE_INTERVAL);
+        keep_alive.tick().await;
+
+        loop {
+            match resume.take() {
+                Some(Resume::Replay(events)) => {
+                    for event in events {
+                        last_sent = event.id;
+                        if kinds.contains(&event.kind) {
+                            yield Ok::<_, Infallible>(event.to_sse());
+                        }
+                    }
+                }
+                Some(Resume::Reset { last_event_id }) => {
+                    last_sent = last_event_id;
+                    yield Ok(reset_sse(last_event_id));
+                }
+                None => {}
+            }
+
+            let received = tokio::select! {
+                received = receiver.recv() => Some(received),
+                _ = keep_alive.tick() => None,
+            };
+
+            match received {
+                // Already sent as part of a replay.
+                Some(Ok(event)) if event.id <= last_sent => {}
+                Some(Ok(event)) => {
+                    last_sent = event.id;
+                    if kinds.contains(&event.kind) {
+                        yield Ok(event.to_sse());
+                    }
+                }
+                Some(Err(RecvError::Lagged(_))) => resume = Some(hub.resume(last_sent)),
+                Some(Err(RecvError::Closed)) => break,
+                None => yield Ok(Bytes::from_static(b": keep-alive\n\n")),
+            }
+        }
+    };
+
+    Ok(HttpResponse::Ok()
+        .insert_header((CONTENT_TYPE, "text/event-stream"))
+        .insert_header((CACHE_CONTROL, "no-cache"))
+        .streaming(stream))
+}
@@ -0,0 +1,171 @@
+use atom_syndication::{ContentBuilder, EntryBuilder, FeedBuilder, LinkBuilder};
+use chrono::{DateTime, Utc};
+use rss::{ChannelBuilder, GuidBuilder, ItemBuilder};
+
+use crate::{markdown, page};
+
+pub const RSS_CONTENT_TYPE: &str = "application/rss+xml; charset=utf-8";
+pub const ATOM_CONTENT_TYPE: &str = "application/atom+xml; charset=utf-8";
+
+/// Number of most recent entries included in each feed.
+pub const ENTRY_LIMIT: u64 = 20;
+
+const FEED_TITLE: &str = "Blog";
+const FEED_DESCRIPTION: &str = "Latest articles";
+
+fn article_url(base_url: &str, article: &entity::articles::Model) -> String {
+    format!("{}{}", base_url, page::article_path(article.id))
+}
+
+fn article_html(article: &entity::articles::Model) -> String {
+    article
+        .body_html
+        .clone()
+        .unwrap_or_else(|| markdown::render_html(&article.body))
+}
+
+fn comment_url(base_url: &str, comment: &entity::comments::Model) -> String {
+    format!(
+        "{}{}#comment-{}",
+        base_url,
+        page::article_path(comment.article_id),
+        comment.id
+    )
+}
+
+/// The feed is as fresh as its most recently updated entry. An empty feed falls
+/// back to `fallback` so that it still carries a stable date.
+fn last_updated<'a>(
+    updated_at: impl Iterator<Item = &'a DateTime<Utc>>,
+    fallback: DateTime<Utc>,
+) -> DateTime<Utc> {
+    updated_at.max().copied().unwrap_or(fallback)
+}
+
+pub fn articles_rss(base_url: &str, articles: &[entity::articles::Model]) -> String {
+    let items = articles
+        .iter()
+        .map(|article| {
+            let url = article_url(base_url, article);
+
+            ItemBuilder::default()
+                .title(article.title.clone())
+                .link(url.clone())
+                .description(article_html(article))
+                .guid(GuidBuilder::default().value(url).permalink(true).build())
+                .pub_date(article.created_at.to_rfc2822())
+                .build()
+        })
+        .collect::<Vec<rss::Item>>();
+
+    let last_build_date = last_updated(
+        articles.iter().map(|article| &article.updated_at),
+        DateTime::<Utc>::default(),
+    );
+
+    ChannelBuilder::default()
+        .title(FEED_TITLE)
+        .link(base_url)
+        .description(FEED_DESCRIPTION)
+        .last_build_date(last_build_date.to_rfc2822())
+        .items(items)
+        .build()
+        .to_string()
+}
+
+pub fn articles_atom(base_url: &str, articles: &[entity::articles::Model]) -> String {
+    let entries = articles
+        .iter()
+        .map(|article| {
+            let url = article_url(base_url, article);
+
+            EntryBuilder::default()
+                .title(article.title.clone())
+                .id(url.clone())
+                .updated(article.updated_at)
+                .published(Some(article.created_at.into()))
+                .link(LinkBuilder::default().href(url).rel("alternate").build())
+                .content(
+                    ContentBuilder::default()
+                        .value(article_html(article))
+                        .content_type("html".to_string())
+                        .build(),
+                )
+                .build()
+        })
+        .collect::<Vec<atom_syndication::Entry>>();
+
+    let updated = last_updated(
+        articles.iter().map(|article| &article.updated_at),
+        DateTime::<Utc>::default(),
+    );
+
+    FeedBuilder::default()
+        .title(FEED_TITLE)
+        .id(format!("{}/feed.atom", base_url))
+        .updated(updated)
+        .link(
+            LinkBuilder::default()
+                .href(format!("{}/feed.atom", base_url))
+                .rel("self")
+                .build(),
+        )
+        .link(
+            LinkBuilder::default()
+                .href(base_url.to_string())
+                .rel("alternate")
+                .build(),
+        )
+        .entries(entries)
+        .build()
+        .to_string()
+}
+
+pub fn comments_atom(
+    base_url: &str,
+    article: &entity::articles::Model,
+    comments: &[entity::comments::Model],
+) -> String {
+    let self_url = format!("{}/articles/{}/comments.atom", base_url, article.id);
+
+    let entries = comments
+        .iter()
+        .map(|comment| {
+            let url = comment_url(base_url, comment);
+
+            EntryBuilder::default()
+                .title(format!("Comment on {}", article.title))
+                .id(url.clone())
+                .updated(comment.updated_at)
+                .published(Some(comment.created_at.into()))
+                .link(LinkBuilder::default().href(url).rel("alternate").build())
+                .content(
+                    ContentBuilder::default()
+                        .value(comment.body.clone())
+                        .content_type("text".to_string())
+                        .build(),
+                )
+                .build()
+        })
+        .collect::<Vec<atom_syndication::Entry>>();
+
+    let updated = last_updated(
+        comments.iter().map(|comment| &comment.updated_at),
+        article.updated_at,
+    );
+
+    FeedBuilder::default()
+        .title(format!("Comments on {}", article.title))
+        .id(self_url.clone())
+        .updated(updated)
+        .link(LinkBuilder::default().href(self_url).rel("self").build())
+        .link(
+            LinkBuilder::default()
+                .href(article_url(base_url, article))
+                .rel("alternate")
+                .build(),
+        )
+        .entries(entries)
+        .build()
+        .to_string()
+}
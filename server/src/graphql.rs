@@ -0,0 +1,592 @@
+use std::{collections::HashMap, sync::Arc};
+
+use actix_web::{post, web, HttpRequest};
+use async_graphql::{
+    dataloader::{DataLoader, Loader},
+    ComplexObject, Context, EmptySubscription, Enum, ErrorExtensions, InputObject, Object, Schema,
+    SimpleObject,
+};
+use async_graphql_actix_web::{GraphQLRequest, GraphQLResponse};
+use chrono::{DateTime, Utc};
+use sea_orm::{DatabaseConnection, DbErr, TryIntoModel};
+
+use crate::{email, middleware::RateLimiter, repository, spam, webhook, AppState};
+
+const MAX_QUERY_DEPTH: usize = 10;
+
+const MAX_QUERY_COMPLEXITY: usize = 200;
+
+pub type BlogSchema = Schema<QueryRoot, MutationRoot, EmptySubscription>;
+
+/// Builds the schema. Resolvers reach the database, the spam pipeline and the
+/// metrics through the `AppState` stored in the schema data.
+pub fn schema(app_state: AppState) -> BlogSchema {
+    Schema::build(QueryRoot, MutationRoot, EmptySubscription)
+        .data(app_state)
+        .limit_depth(MAX_QUERY_DEPTH)
+        .limit_complexity(MAX_QUERY_COMPLEXITY)
+        .finish()
+}
+
+#[post("/graphql")]
+async fn graphql(
+    schema: web::Data<BlogSchema>,
+    data: web::Data<AppState>,
+    rate_limiter: web::Data<RateLimiter>,
+    http_request: HttpRequest,
+    request: GraphQLRequest,
+) -> GraphQLResponse {
+    // Loaders cache what they fetch, so each request gets its own.
+    let comments_loader = DataLoader::new(
+        ApprovedCommentsLoader {
+            database_connection: data.database_connection.clone(),
+        },
+        actix_web::rt::spawn,
+    );
+
+    // The whole request counts once against the write limit, so each comment
+    // it creates also takes a token from the client's comment limit.
+    let comment_rate_limit = CommentRateLimit {
+        rate_limiter: rate_limiter.get_ref().clone(),
+        client: rate_limiter.client_key(&http_request),
+    };
+
+    schema
+        .execute(
+            request
+                .into_inner()
+                .data(comments_loader)
+                .data(comment_rate_limit),
+        )
+        .await
+        .into()
+}
+
+/// The comment rate limit of the client a request came from.
+struct CommentRateLimit {
+    rate_limiter: RateLimiter,
+    client: String,
+}
+
+impl CommentRateLimit {
+    fn acquire(&self) -> async_graphql::Result<()> {
+        if self.rate_limiter.acquire("comments", self.client.clone()) {
+            Ok(())
+        } else {
+            Err(error("TOO_MANY_REQUESTS", "Too Many Requests"))
+        }
+    }
+}
+
+/// Errors carry the same codes as `HttpErrorResponse` in their extensions.
+fn error(code: &'static str, message: &str) -> async_graphql::Error {
+    async_graphql::Error::new(message).extend_with(|_, extensions| extensions.set("code", code))
+}
+
+fn bad_request(message: &str) -> async_graphql::Error {
+    error("BAD_REQUEST", message)
+}
+
+fn not_found() -> async_graphql::Error {
+    error("NOT_FOUND", "Not Found")
+}
+
+fn internal_server_error(err: impl std::fmt::Display) -> async_graphql::Error {
+    tracing::error!(error = %err, "graphql resolver failed");
+    error("INTERNAL_SERVER_ERROR", "Internal Server Error")
+}
+
+#[derive(Enum, Clone, Copy, PartialEq, Eq)]
+#[graphql(remote = "entity::sea_orm_active_enums::ModerationStatus")]
+enum ModerationStatus {
+    Pending,
+    Approved,
+    Rejected,
+    Spam,
+}
+
+#[derive(SimpleObject)]
+#[graphql(complex)]
+struct Article {
+    id: i32,
+    title: String,
+    body: String,
+    body_html: String,
+    category_id: i32,
+    tags: Vec<String>,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+}
+
+impl Article {
+    fn new(article: entity::articles::Model, tags: &[entity::tags::Model]) -> Self {
+        Self {
+            id: article.id,
+            title: article.title,
+            body: article.body,
+            body_html: article.body_html.unwrap_or_default(),
+            category_id: article.category_id,
+            tags: tags.iter().map(|tag| tag.name.clone()).collect(),
+            created_at: article.created_at,
+            updated_at: article.updated_at,
+        }
+    }
+}
+
+#[ComplexObject]
+impl Article {
+    /// Approved comments, oldest first. Replies point at their parent through
+    /// `parentId`.
+    async fn comments(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<Comment>> {
+        let comments = ctx
+            .data_unchecked::<DataLoader<ApprovedCommentsLoader>>()
+            .load_one(self.id)
+            .await
+            .map_err(internal_server_error)?
+            .unwrap_or_default();
+
+        Ok(comments.into_iter().map(Comment::from).collect())
+    }
+}
+
+#[derive(SimpleObject)]
+struct Comment {
+    id: i32,
+    article_id: i32,
+    parent_id: Option<i32>,
+    body: String,
+    moderation_status: ModerationStatus,
+    created_at: DateTime<Utc>,
+}
+
+impl From<entity::comments::Model> for Comment {
+    fn from(comment: entity::comments::Model) -> Self {
+        Self {
+            id: comment.id,
+            article_id: comment.article_id,
+            parent_id: comment.parent_id,
+            body: comment.body,
+            moderation_status: comment.moderation_status.into(),
+            created_at: comment.created_at,
+        }
+    }
+}
+
+#[derive(InputObject)]
+struct ArticleInput {
+    title: String,
+    body: String,
+    category_id: i32,
+    /// Replaces the article's tags. Left untouched on update when omitted.
+    tags: Option<Vec<String>>,
+    /// Emailed about new comments. Left untouched on update when omitted.
+    author_email: Option<String>,
+}
+
+#[derive(InputObject)]
+struct CommentInput {
+    parent_id: Option<i32>,
+    body: String,
+}
+
+/// Batches the comment lookups of every article in a query into one
+/// `article_id IN (...)` query.
+struct ApprovedCommentsLoader {
+    database_connection: DatabaseConnection,
+}
+
+#[async_trait::async_trait]
+impl Loader<i32> for ApprovedCommentsLoader {
+    type Value = Vec<entity::comments::Model>;
+    type Error = Arc<DbErr>;
+
+    async fn load(&self, keys: &[i32]) -> Result<HashMap<i32, Self::Value>, Self::Error> {
+        let comments_repository =
+            repository::CommentsRepository::new(self.database_connection.clone());
+
+        let comments = comments_repository
+            .find_approved_by_article_ids(keys.to_vec())
+            .await
+            .map_err(Arc::new)?;
+
+        let mut comments_by_article: HashMap<i32, Self::Value> = HashMap::new();
+        for comment in comments {
+            comments_by_article
+                .entry(comment.article_id)
+                .or_default()
+                .push(comment);
+        }
+
+        Ok(comments_by_article)
+    }
+}
+
+async fn find_article(
+    database_connection: &DatabaseConnection,
+    id: i32,
+) -> async_graphql::Result<Option<Article>> {
+    let articles_repository = repository::ArticlesRepository::new(database_connection.clone());
+
+    match articles_repository.find_by_id(id).await {
+        Ok(Some(article)) => match articles_repository.find_tags(&article).await {
+            Ok(tags) => Ok(Some(Article::new(article, &tags))),
+            Err(err) => Err(internal_server_error(err)),
+        },
+        Ok(None) => Ok(None),
+        Err(err) => Err(internal_server_error(err)),
+    }
+}
+
+async fn validate_category_id(
+    database_connection: &DatabaseConnection,
+    category_id: i32,
+) -> async_graphql::Result<()> {
+    let categories_repository = repository::CategoriesRepository::new(database_connection.clone());
+
+    match categories_repository.find_by_id(category_id).await {
+        Ok(Some(_)) => Ok(()),
+        Ok(None) => Err(bad_request("categoryId does not exist")),
+        Err(err) => Err(internal_server_error(err)),
+    }
+}
+
+fn validate_author_email(author_email: Option<&str>) -> async_graphql::Result<Option<String>> {
+    match author_email {
+        Some(author_email) => match email::normalize_address(author_email) {
+            Some(author_email) if author_email.len() <= 254 => Ok(Some(author_email)),
+            _ => Err(bad_request("authorEmail must be a valid email address")),
+        },
+        None => Ok(None),
+    }
+}
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    /// Every article, optionally only those tagged `tag`.
+    async fn articles(
+        &self,
+        ctx: &Context<'_>,
+        tag: Option<String>,
+    ) -> async_graphql::Result<Vec<Article>> {
+        let data = ctx.data_unchecked::<AppState>();
+        let articles_repository =
+            repository::ArticlesRepository::new(data.database_connection.clone());
+
+        let articles = match tag {
+            Some(tag) => {
+                articles_repository
+                    .find_all_with_tags_by_tag_name(&tag)
+                    .await
+            }
+            None => articles_repository.find_all_with_tags().await,
+        };
+
+        match articles {
+            Ok(articles) => Ok(articles
+                .into_iter()
+                .map(|(article, tags)| Article::new(article, &tags))
+                .collect()),
+            Err(err) => Err(internal_server_error(err)),
+        }
+    }
+
+    async fn article(&self, ctx: &Context<'_>, id: i32) -> async_graphql::Result<Option<Article>> {
+        let data = ctx.data_unchecked::<AppState>();
+
+        find_article(&data.database_connection, id).await
+    }
+}
+
+pub struct MutationRoot;
+
+#[Object]
+impl MutationRoot {
+    async fn create_article(
+        &self,
+        ctx: &Context<'_>,
+        input: ArticleInput,
+    ) -> async_graphql::Result<Article> {
+        let data = ctx.data_unchecked::<AppState>();
+        let database_connection = &data.database_connection;
+
+        validate_category_id(database_connection, input.category_id).await?;
+        let author_email = validate_author_email(input.author_email.as_deref())?;
+
+        let articles_repository = repository::ArticlesRepository::cached(
+            database_connection.clone(),
+            data.article_cache.clone(),
+        );
+
+        let form = entity::articles::Model {
+            id: 0,
+            title: input.title,
+            body: input.body,
+            category_id: input.category_id,
+            body_html: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            author_email,
+        };
+
+        let (article, tags) = articles_repository
+            .create(form, &input.tags.unwrap_or_default())
+            .await
+            .and_then(|(article, tags)| Ok((article.try_into_model()?, tags)))
+            .map_err(internal_server_error)?;
+        data.metrics.article_created();
+        data.article_events.created(&article);
+        data.webhooks
+            .article(webhook::WebhookEvent::ArticleCreated, &article);
+
+        Ok(Article::new(article, &tags))
+    }
+
+    async fn update_article(
+        &self,
+        ctx: &Context<'_>,
+        id: i32,
+        input: ArticleInput,
+    ) -> async_graphql::Result<Article> {
+        let data = ctx.data_unchecked::<AppState>();
+        let database_connection = &data.database_connection;
+
+        let articles_repository = repository::ArticlesRepository::cached(
+            database_connection.clone(),
+            data.article_cache.clone(),
+        );
+
+        let article = match articles_repository.find_by_id(id).await {
+            Ok(Some(article)) => article,
+            Ok(None) => return Err(not_found()),
+            Err(err) => return Err(internal_server_error(err)),
+        };
+
+        validate_category_id(database_connection, input.category_id).await?;
+        let author_email =
+            validate_author_email(input.author_email.as_deref())?.or(article.author_email);
+
+        let form = entity::articles::Model {
+            id,
+            title: input.title,
+            body: input.body,
+            category_id: input.category_id,
+            body_html: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            author_email,
+        };
+
+        let article = articles_repository
+            .update(form)
+            .await
+            .and_then(|article| article.try_into_model())
+            .map_err(internal_server_error)?;
+
+        if let Some(tags) = input.tags {
+            let tags_repository = repository::TagsRepository::cached(
+                database_connection.clone(),
+                data.article_cache.clone(),
+            );
+
+            if let Err(err) = tags_repository.replace_for_article(id, &tags).await {
+                return Err(internal_server_error(err));
+            }
+        }
+
+        data.article_events.updated(&article);
+        data.webhooks
+            .article(webhook::WebhookEvent::ArticleUpdated, &article);
+
+        find_article(database_connection, id)
+            .await?
+            .ok_or_else(not_found)
+    }
+
+    async fn delete_article(&self, ctx: &Context<'_>, id: i32) -> async_graphql::Result<bool> {
+        let data = ctx.data_unchecked::<AppState>();
+        let articles_repository = repository::ArticlesRepository::cached(
+            data.database_connection.clone(),
+            data.article_cache.clone(),
+        );
+
+        match articles_repository.find_by_id(id).await {
+            Ok(Some(article)) => match articles_repository.delete(id).await {
+                Ok(_) => {
+                    data.article_events.deleted(id);
+                    data.webhooks
+                        .article(webhook::WebhookEvent::ArticleDeleted, &article);
+                    data.attachments.article_deleted(id);
+                    Ok(true)
+                }
+                Err(err) => Err(internal_server_error(err)),
+            },
+            Ok(None) => Err(not_found()),
+            Err(err) => Err(internal_server_error(err)),
+        }
+    }
+
+    /// Creates a comment. Depending on the spam filters and the moderation
+    /// settings, it may be held back and not appear under the article yet.
+    async fn create_comment(
+        &self,
+        ctx: &Context<'_>,
+        article_id: i32,
+        input: CommentInput,
+    ) -> async_graphql::Result<Comment> {
+        ctx.data_unchecked::<CommentRateLimit>().acquire()?;
+
+        let data = ctx.data_unchecked::<AppState>();
+        let database_connection = &data.database_connection;
+
+        let articles_repository = repository::ArticlesRepository::new(database_connection.clone());
+        let comments_repository = repository::CommentsRepository::new(database_connection.clone());
+
+        match articles_repository.find_by_id(article_id).await {
+            Ok(Some(_)) => {}
+            Ok(None) => return Err(not_found()),
+            Err(err) => return Err(internal_server_error(err)),
+        }
+
+        if let Some(parent_id) = input.parent_id {
+            let comments = comments_repository
+                .find_approved_by_article_id(article_id)
+                .await
+                .map_err(internal_server_error)?;
+
+            if !comments.iter().any(|comment| comment.id == parent_id) {
+                return Err(bad_request(
+                    "parentId must refer to a comment on the same article",
+                ));
+            }
+
+            if crate::handler::comment_depth(&comments, parent_id) >= data.comment_max_depth {
+                return Err(bad_request("replies are nested too deeply"));
+            }
+        }
+
+        let candidate = spam::CommentCandidate {
+            article_id,
+            body: &input.body,
+        };
+
+        let moderation_status = data
+            .spam_pipeline
+            .initial_moderation_status(database_connection, &candidate)
+            .await
+            .map_err(internal_server_error)?;
+
+        let form = entity::comments::Model {
+            id: 0,
+            article_id,
+            body: input.body,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            parent_id: input.parent_id,
+            moderation_status,
+            moderated_at: None,
+        };
+
+        let comment = comments_repository
+            .create(form)
+            .await
+            .and_then(|comment| comment.try_into_model())
+            .map_err(internal_server_error)?;
+        data.metrics.comment_created(moderation_status);
+        data.comment_hub.saved(&comment, None);
+        data.comment_notifier.saved(&comment, None);
+        data.webhooks
+            .comment(webhook::WebhookEvent::CommentCreated, &comment);
+
+        Ok(Comment::from(comment))
+    }
+
+    async fn update_comment(
+        &self,
+        ctx: &Context<'_>,
+        article_id: i32,
+        id: i32,
+        body: String,
+    ) -> async_graphql::Result<Comment> {
+        let data = ctx.data_unchecked::<AppState>();
+        let comments_repository =
+            repository::CommentsRepository::new(data.database_connection.clone());
+
+        let comment = match comments_repository
+            .find_by_article_id_and_id(article_id, id)
+            .await
+        {
+            Ok(Some(comment)) => comment,
+            Ok(None) => return Err(not_found()),
+            Err(err) => return Err(internal_server_error(err)),
+        };
+
+        let candidate = spam::CommentCandidate {
+            article_id,
+            body: &body,
+        };
+
+        let moderation_status = data
+            .spam_pipeline
+            .edited_moderation_status(
+                &data.database_connection,
+                &candidate,
+                comment.moderation_status,
+            )
+            .await
+            .map_err(internal_server_error)?;
+
+        // A moderator's decision was about the old body.
+        let form = entity::comments::Model {
+            body,
+            updated_at: Utc::now(),
+            moderation_status,
+            moderated_at: None,
+            ..comment.clone()
+        };
+
+        let updated = comments_repository
+            .update(form)
+            .await
+            .and_then(|comment| comment.try_into_model())
+            .map_err(internal_server_error)?;
+        data.spam_pipeline.forget(&comment);
+        data.comment_hub
+            .saved(&updated, Some(comment.moderation_status));
+        data.comment_notifier
+            .saved(&updated, Some(comment.moderation_status));
+        data.webhooks
+            .comment(webhook::WebhookEvent::CommentUpdated, &updated);
+
+        Ok(Comment::from(updated))
+    }
+
+    async fn delete_comment(
+        &self,
+        ctx: &Context<'_>,
+        article_id: i32,
+        id: i32,
+    ) -> async_graphql::Result<bool> {
+        let data = ctx.data_unchecked::<AppState>();
+        let comments_repository =
+            repository::CommentsRepository::new(data.database_connection.clone());
+
+        match comments_repository
+            .find_by_article_id_and_id(article_id, id)
+            .await
+        {
+            Ok(Some(comment)) => match comments_repository.delete(article_id, id).await {
+                Ok(_) => {
+                    data.comment_hub.deleted(&comment);
+                    data.webhooks
+                        .comment(webhook::WebhookEvent::CommentDeleted, &comment);
+                    Ok(true)
+                }
+                Err(err) => Err(internal_server_error(err)),
+            },
+            Ok(None) => Err(not_found()),
+            Err(err) => Err(internal_server_error(err)),
+        }
+    }
+}
@@ -1,15 +1,32 @@
+use std::collections::{BTreeMap, HashMap};
+
+use actix_multipart::{Field, Multipart};
 use actix_web::{
     delete, get,
-    http::{header::ContentType, StatusCode},
-    patch, post, web, HttpResponse, Responder,
+    http::{
+        header::{self, ContentType, EntityTag, Header, IfNoneMatch},
+        StatusCode,
+    },
+    patch, post, web, HttpMessage, HttpRequest, HttpResponse,
 };
+use chrono::{DateTime, Utc};
 use derive_more::Display;
+use futures_util::StreamExt;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use similar::TextDiff;
+use utoipa::{IntoParams, OpenApi, ToSchema};
 
-use crate::repository;
+use crate::{
+    attachment, email, feed, imaging, markdown, metrics, middleware, repository, sitemap, spam,
+    storage, webhook,
+};
+use entity::sea_orm_active_enums::{JobStatus, ModerationStatus, WebhookDeliveryStatus};
+use migration::MigratorTrait;
+use sea_orm::{ConnectionTrait, Statement, TryIntoModel};
 
-#[derive(Serialize)]
-struct HttpErrorResponse {
+#[derive(Serialize, ToSchema)]
+pub(crate) struct HttpErrorResponse {
     code: String,
     message: String,
 }
@@ -22,10 +39,34 @@ impl HttpErrorResponse {
         }
     }
 
+    fn bad_request(message: &str) -> Self {
+        Self::new("BAD_REQUEST", message)
+    }
+
     fn not_found() -> Self {
         Self::new("NOT_FOUND", "Not Found")
     }
 
+    fn conflict(message: &str) -> Self {
+        Self::new("CONFLICT", message)
+    }
+
+    fn payload_too_large(message: &str) -> Self {
+        Self::new("PAYLOAD_TOO_LARGE", message)
+    }
+
+    fn unsupported_media_type(message: &str) -> Self {
+        Self::new("UNSUPPORTED_MEDIA_TYPE", message)
+    }
+
+    fn service_unavailable(message: &str) -> Self {
+        Self::new("SERVICE_UNAVAILABLE", message)
+    }
+
+    pub(crate) fn too_many_requests() -> Self {
+        Self::new("TOO_MANY_REQUESTS", "Too Many Requests")
+    }
+
     fn internal_server_error() -> Self {
         Self::new("INTERNAL_SERVER_ERROR", "Internal Server Error")
     }
@@ -36,8 +77,23 @@ pub enum AppErrorKind {
     #[display(fmt = "internal server error")]
     InternalServerError,
 
+    #[display(fmt = "bad request")]
+    BadRequest,
+
     #[display(fmt = "not found")]
     NotFound,
+
+    #[display(fmt = "conflict")]
+    Conflict,
+
+    #[display(fmt = "payload too large")]
+    PayloadTooLarge,
+
+    #[display(fmt = "unsupported media type")]
+    UnsupportedMediaType,
+
+    #[display(fmt = "service unavailable")]
+    ServiceUnavailable,
 }
 
 #[derive(Debug, Display)]
@@ -55,12 +111,47 @@ impl AppError {
         }
     }
 
+    pub fn bad_request(message: &str) -> Self {
+        Self {
+            kind: AppErrorKind::BadRequest,
+            err: anyhow::anyhow!(message.to_string()),
+        }
+    }
+
     pub fn not_found() -> Self {
         Self {
             kind: AppErrorKind::NotFound,
             err: anyhow::anyhow!("Not Found"),
         }
     }
+
+    pub fn conflict(message: &str) -> Self {
+        Self {
+            kind: AppErrorKind::Conflict,
+            err: anyhow::anyhow!(message.to_string()),
+        }
+    }
+
+    pub fn payload_too_large(message: &str) -> Self {
+        Self {
+            kind: AppErrorKind::PayloadTooLarge,
+            err: anyhow::anyhow!(message.to_string()),
+        }
+    }
+
+    pub fn unsupported_media_type(message: &str) -> Self {
+        Self {
+            kind: AppErrorKind::UnsupportedMediaType,
+            err: anyhow::anyhow!(message.to_string()),
+        }
+    }
+
+    pub fn service_unavailable(message: &str) -> Self {
+        Self {
+            kind: AppErrorKind::ServiceUnavailable,
+            err: anyhow::anyhow!(message.to_string()),
+        }
+    }
 }
 
 impl From<anyhow::Error> for AppError {
@@ -78,55 +169,640 @@ impl actix_web::error::ResponseError for AppError {
             .insert_header(ContentType::json())
             .json(match self.kind {
                 AppErrorKind::InternalServerError => HttpErrorResponse::internal_server_error(),
+                AppErrorKind::BadRequest => HttpErrorResponse::bad_request(&self.err.to_string()),
                 AppErrorKind::NotFound => HttpErrorResponse::not_found(),
+                AppErrorKind::Conflict => HttpErrorResponse::conflict(&self.err.to_string()),
+                AppErrorKind::PayloadTooLarge => {
+                    HttpErrorResponse::payload_too_large(&self.err.to_string())
+                }
+                AppErrorKind::UnsupportedMediaType => {
+                    HttpErrorResponse::unsupported_media_type(&self.err.to_string())
+                }
+                AppErrorKind::ServiceUnavailable => {
+                    HttpErrorResponse::service_unavailable(&self.err.to_string())
+                }
             })
     }
 
     fn status_code(&self) -> StatusCode {
         match self.kind {
             AppErrorKind::InternalServerError => StatusCode::INTERNAL_SERVER_ERROR,
+            AppErrorKind::BadRequest => StatusCode::BAD_REQUEST,
             AppErrorKind::NotFound => StatusCode::NOT_FOUND,
+            AppErrorKind::Conflict => StatusCode::CONFLICT,
+            AppErrorKind::PayloadTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+            AppErrorKind::UnsupportedMediaType => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            AppErrorKind::ServiceUnavailable => StatusCode::SERVICE_UNAVAILABLE,
         }
     }
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 struct ArticleIndexResponse {
     id: i32,
     title: String,
     body: String,
+    category_id: i32,
+    tags: Vec<String>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 struct ArticleShowResponse {
     id: i32,
     title: String,
     body: String,
+    body_html: String,
+    category_id: i32,
+    tags: Vec<String>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct ArticleIndexQuery {
+    tag: Option<String>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 struct ArticleForm {
     title: String,
     body: String,
+    /// Defaults to the "Uncategorized" root category on create, and is left
+    /// untouched on update when omitted.
+    category_id: Option<i32>,
+    tags: Option<Vec<String>>,
+    /// Emailed about new comments. Left untouched on update when omitted.
+    author_email: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct AttachmentResponse {
+    id: i32,
+    article_id: i32,
+    file_name: String,
+    content_type: String,
+    size: i64,
+    /// Pixel dimensions of images; `null` for other files.
+    width: Option<i32>,
+    height: Option<i32>,
+    url: String,
+    /// Resized copies of images, narrowest first. Made in the background, so
+    /// empty right after the upload.
+    variants: Vec<AttachmentVariantResponse>,
+    /// One `srcset` attribute value per image format, original included, in
+    /// the order `<source>` elements should list them.
+    srcset: Vec<AttachmentSrcsetResponse>,
+    created_at: DateTime<Utc>,
+}
+
+impl AttachmentResponse {
+    fn new(
+        attachment: entity::attachments::Model,
+        variants: Vec<entity::attachment_variants::Model>,
+    ) -> Self {
+        let url = attachment::attachment_path(attachment.id);
+
+        let mut candidates = variants
+            .iter()
+            .map(|variant| {
+                (
+                    variant.content_type.as_str(),
+                    variant.width,
+                    attachment::variant_path(attachment.id, variant.id),
+                )
+            })
+            .collect::<Vec<(&str, i32, String)>>();
+        if let Some(width) = attachment.width {
+            candidates.push((attachment.content_type.as_str(), width, url.clone()));
+        }
+        // WebP first, as browsers take the first `<source>` they support.
+        candidates.sort_by_key(|&(content_type, width, _)| {
+            (content_type != "image/webp", content_type, width)
+        });
+
+        let mut srcset = Vec::<AttachmentSrcsetResponse>::new();
+        for (content_type, width, url) in candidates {
+            let candidate = format!("{url} {width}w");
+            match srcset.last_mut() {
+                Some(last) if last.content_type == content_type => {
+                    last.srcset.push_str(", ");
+                    last.srcset.push_str(&candidate);
+                }
+                _ => srcset.push(AttachmentSrcsetResponse {
+                    content_type: content_type.to_string(),
+                    srcset: candidate,
+                }),
+            }
+        }
+
+        Self {
+            url,
+            srcset,
+            variants: variants
+                .into_iter()
+                .map(AttachmentVariantResponse::from)
+                .collect(),
+            id: attachment.id,
+            article_id: attachment.article_id,
+            file_name: attachment.file_name,
+            content_type: attachment.content_type,
+            size: attachment.size,
+            width: attachment.width,
+            height: attachment.height,
+            created_at: attachment.created_at,
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+struct AttachmentVariantResponse {
+    id: i32,
+    content_type: String,
+    width: i32,
+    height: i32,
+    size: i64,
+    url: String,
+}
+
+impl From<entity::attachment_variants::Model> for AttachmentVariantResponse {
+    fn from(variant: entity::attachment_variants::Model) -> Self {
+        Self {
+            url: attachment::variant_path(variant.attachment_id, variant.id),
+            id: variant.id,
+            content_type: variant.content_type,
+            width: variant.width,
+            height: variant.height,
+            size: variant.size,
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+struct AttachmentSrcsetResponse {
+    content_type: String,
+    /// Such as `/attachments/1/variants/2 320w, /attachments/1 800w`.
+    srcset: String,
+}
+
+/// Only documents the request body of `attachments_create`, which reads the
+/// multipart stream itself.
+#[allow(dead_code)]
+#[derive(ToSchema)]
+struct AttachmentUploadForm {
+    /// One or more files, each in its own part.
+    #[schema(value_type = Vec<String>, format = Binary)]
+    file: Vec<Vec<u8>>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct ArticleRevisionIndexResponse {
+    revision: i32,
+    title: String,
+    created_at: DateTime<Utc>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct ArticleRevisionDiffQuery {
+    against: Option<i32>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
+struct ArticleRevisionDiffResponse {
+    revision: i32,
+    against: Option<i32>,
+    diff: String,
+}
+
+#[derive(Serialize, ToSchema)]
 struct CommentIndexResponse {
+    id: i32,
+    parent_id: Option<i32>,
+    body: String,
+}
+
+#[derive(Serialize, ToSchema)]
+struct CommentTreeResponse {
     id: i32,
     body: String,
+    replies: Vec<CommentTreeResponse>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 struct CommentShowResponse {
     id: i32,
+    parent_id: Option<i32>,
     body: String,
+    moderation_status: ModerationStatus,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct CommentIndexQuery {
+    format: Option<String>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 struct CommentForm {
+    parent_id: Option<i32>,
+    body: String,
+}
+
+#[derive(Deserialize, ToSchema)]
+struct CommentSubscriptionForm {
+    email: String,
+}
+
+#[derive(Serialize, ToSchema)]
+struct ModerationCommentResponse {
+    id: i32,
+    article_id: i32,
+    parent_id: Option<i32>,
     body: String,
+    moderation_status: ModerationStatus,
+    created_at: DateTime<Utc>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct ModerationCommentsQuery {
+    status: Option<ModerationStatus>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct BlogSettingsResponse {
+    comment_auto_approve: bool,
+}
+
+#[derive(Deserialize, ToSchema)]
+struct BlogSettingsForm {
+    comment_auto_approve: bool,
+}
+
+#[derive(Serialize, ToSchema)]
+struct TagIndexResponse {
+    id: i32,
+    name: String,
+    article_count: i64,
+}
+
+#[derive(Serialize, ToSchema)]
+struct CategoryIndexResponse {
+    id: i32,
+    parent_id: Option<i32>,
+    name: String,
+}
+
+#[derive(Serialize, ToSchema)]
+struct CategoryShowResponse {
+    id: i32,
+    parent_id: Option<i32>,
+    name: String,
+}
+
+#[derive(Deserialize, ToSchema)]
+struct CategoryForm {
+    parent_id: Option<i32>,
+    name: String,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct CategoryArticlesQuery {
+    include_descendants: Option<bool>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct HealthResponse {
+    status: &'static str,
+}
+
+#[derive(Serialize, ToSchema)]
+struct ComponentHealthResponse {
+    status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
+}
+
+impl ComponentHealthResponse {
+    fn ok() -> Self {
+        Self {
+            status: "ok",
+            message: None,
+        }
+    }
+
+    fn error(message: String) -> Self {
+        Self {
+            status: "error",
+            message: Some(message),
+        }
+    }
+
+    fn is_ok(&self) -> bool {
+        self.message.is_none()
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+struct ReadinessResponse {
+    status: &'static str,
+    components: BTreeMap<&'static str, ComponentHealthResponse>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct WebhookResponse {
+    id: i32,
+    url: String,
+    events: Vec<String>,
+    active: bool,
+    /// Only returned when the webhook is created or its secret is changed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    secret: Option<String>,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+}
+
+impl WebhookResponse {
+    fn new(webhook: entity::webhooks::Model, show_secret: bool) -> Self {
+        Self {
+            id: webhook.id,
+            url: webhook.url,
+            events: webhook.events.split(',').map(str::to_string).collect(),
+            active: webhook.active,
+            secret: show_secret.then_some(webhook.secret),
+            created_at: webhook.created_at,
+            updated_at: webhook.updated_at,
+        }
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+struct WebhookForm {
+    url: String,
+    /// Event names such as `article.created`, or `*` for every event.
+    events: Vec<String>,
+    active: Option<bool>,
+    /// Generated on creation and kept on update when omitted.
+    secret: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct WebhookDeliveryResponse {
+    id: i32,
+    webhook_id: i32,
+    event: String,
+    status: WebhookDeliveryStatus,
+    attempts: i32,
+    next_attempt_at: Option<DateTime<Utc>>,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+}
+
+impl From<entity::webhook_deliveries::Model> for WebhookDeliveryResponse {
+    fn from(delivery: entity::webhook_deliveries::Model) -> Self {
+        Self {
+            id: delivery.id,
+            webhook_id: delivery.webhook_id,
+            event: delivery.event,
+            status: delivery.status,
+            attempts: delivery.attempts,
+            next_attempt_at: delivery.next_attempt_at,
+            created_at: delivery.created_at,
+            updated_at: delivery.updated_at,
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+struct WebhookDeliveryAttemptResponse {
+    attempt: i32,
+    response_status: Option<i32>,
+    error: Option<String>,
+    duration_ms: i32,
+    created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct WebhookDeliveryShowResponse {
+    #[serde(flatten)]
+    delivery: WebhookDeliveryResponse,
+    /// The JSON body sent to the webhook.
+    #[schema(value_type = Object)]
+    payload: serde_json::Value,
+    attempt_history: Vec<WebhookDeliveryAttemptResponse>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct JobResponse {
+    id: i32,
+    kind: String,
+    #[schema(value_type = Object)]
+    payload: serde_json::Value,
+    status: JobStatus,
+    attempts: i32,
+    max_attempts: i32,
+    run_at: DateTime<Utc>,
+    locked_at: Option<DateTime<Utc>>,
+    last_error: Option<String>,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+}
+
+impl From<entity::jobs::Model> for JobResponse {
+    fn from(job: entity::jobs::Model) -> Self {
+        Self {
+            id: job.id,
+            kind: job.kind,
+            payload: serde_json::from_str(&job.payload).unwrap_or_default(),
+            status: job.status,
+            attempts: job.attempts,
+            max_attempts: job.max_attempts,
+            run_at: job.run_at,
+            locked_at: job.locked_at,
+            last_error: job.last_error,
+            created_at: job.created_at,
+            updated_at: job.updated_at,
+        }
+    }
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct JobIndexQuery {
+    status: Option<JobStatus>,
+    kind: Option<String>,
+}
+
+fn tag_names(tags: &[entity::tags::Model]) -> Vec<String> {
+    tags.iter().map(|tag| tag.name.clone()).collect()
+}
+
+fn article_index_responses(
+    articles: &[(entity::articles::Model, Vec<entity::tags::Model>)],
+) -> Vec<ArticleIndexResponse> {
+    articles
+        .iter()
+        .map(|(article, tags)| ArticleIndexResponse {
+            id: article.id,
+            title: article.title.clone(),
+            body: article.body.clone(),
+            category_id: article.category_id,
+            tags: tag_names(tags),
+        })
+        .collect()
+}
+
+fn revision_text(revision: Option<&entity::article_revisions::Model>) -> String {
+    match revision {
+        Some(revision) => format!("{}\n\n{}\n", revision.title, revision.body),
+        None => String::new(),
+    }
+}
+
+/// Line-level unified diff from `against` to `revision`, covering both the title
+/// and the body. A missing `against` diffs against an empty article.
+fn revision_diff(
+    against: Option<&entity::article_revisions::Model>,
+    revision: &entity::article_revisions::Model,
+) -> String {
+    let old = revision_text(against);
+    let new = revision_text(Some(revision));
+    let old_header = match against {
+        Some(against) => format!("revision {}", against.revision),
+        None => "/dev/null".to_string(),
+    };
+    let new_header = format!("revision {}", revision.revision);
+
+    TextDiff::from_lines(&old, &new)
+        .unified_diff()
+        .header(&old_header, &new_header)
+        .to_string()
+}
+
+/// Nests `comments` under their parents, keeping each level in the order the
+/// comments were given.
+fn comment_tree(comments: &[entity::comments::Model]) -> Vec<CommentTreeResponse> {
+    fn build(
+        parent_id: Option<i32>,
+        children: &HashMap<Option<i32>, Vec<&entity::comments::Model>>,
+    ) -> Vec<CommentTreeResponse> {
+        children
+            .get(&parent_id)
+            .map(|comments| {
+                comments
+                    .iter()
+                    .map(|comment| CommentTreeResponse {
+                        id: comment.id,
+                        body: comment.body.clone(),
+                        replies: build(Some(comment.id), children),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    let mut children: HashMap<Option<i32>, Vec<&entity::comments::Model>> = HashMap::new();
+    for comment in comments {
+        children.entry(comment.parent_id).or_default().push(comment);
+    }
+
+    build(None, &children)
+}
+
+/// Depth of the comment `id` within `comments`, where a top-level comment has a
+/// depth of 1.
+pub(crate) fn comment_depth(comments: &[entity::comments::Model], id: i32) -> usize {
+    let parent_ids = comments
+        .iter()
+        .map(|comment| (comment.id, comment.parent_id))
+        .collect::<HashMap<i32, Option<i32>>>();
+
+    let mut depth = 1;
+    let mut current = parent_ids.get(&id).copied().flatten();
+    while let Some(parent_id) = current {
+        depth += 1;
+        current = parent_ids.get(&parent_id).copied().flatten();
+    }
+    depth
+}
+
+/// Builds an XML response tagged with a strong ETag derived from its body, or
+/// an empty `304 Not Modified` when the client already holds that version.
+fn xml_response(request: &HttpRequest, content_type: &str, body: String) -> HttpResponse {
+    let etag = EntityTag::new_strong(format!("{:x}", Sha256::digest(body.as_bytes())));
+
+    let not_modified = match IfNoneMatch::parse(request) {
+        Ok(IfNoneMatch::Any) => true,
+        Ok(IfNoneMatch::Items(items)) => items.iter().any(|item| item.weak_eq(&etag)),
+        Err(_) => false,
+    };
+
+    if not_modified {
+        return HttpResponse::NotModified()
+            .insert_header(header::ETag(etag))
+            .finish();
+    }
+
+    HttpResponse::Ok()
+        .insert_header((header::CONTENT_TYPE, content_type))
+        .insert_header(header::ETag(etag))
+        .body(body)
+}
+
+async fn validate_category_id(
+    categories_repository: &repository::CategoriesRepository,
+    category_id: i32,
+) -> Result<(), AppError> {
+    match categories_repository.find_by_id(category_id).await {
+        Ok(Some(_)) => Ok(()),
+        Ok(None) => Err(AppError::bad_request("category_id does not exist")),
+        Err(err) => Err(AppError::internal_server_error(err.into())),
+    }
+}
+
+/// Returns `email` in the form it is stored in, or a bad request naming
+/// `field` if it is not a valid address.
+fn validate_email(field: &str, email: &str) -> Result<String, AppError> {
+    match email::normalize_address(email) {
+        Some(email) if email.len() <= 254 => Ok(email),
+        _ => Err(AppError::bad_request(&format!(
+            "{field} must be a valid email address"
+        ))),
+    }
+}
+
+/// Checks `webhook_form` and returns its events in the form they are stored in.
+fn validate_webhook_form(webhook_form: &WebhookForm) -> Result<String, AppError> {
+    match reqwest::Url::parse(&webhook_form.url) {
+        Ok(url) if matches!(url.scheme(), "http" | "https") => {}
+        _ => {
+            return Err(AppError::bad_request(
+                "url must be an absolute http or https URL",
+            ))
+        }
+    }
+
+    if webhook_form.events.is_empty() {
+        return Err(AppError::bad_request("events must not be empty"));
+    }
+    for event in &webhook_form.events {
+        if event != webhook::ALL_EVENTS && webhook::WebhookEvent::parse(event).is_none() {
+            return Err(AppError::bad_request(&format!("unknown event: {event}")));
+        }
+    }
+
+    if let Some(secret) = &webhook_form.secret {
+        if secret.is_empty() || secret.len() > 64 {
+            return Err(AppError::bad_request(
+                "secret must be between 1 and 64 characters long",
+            ));
+        }
+    }
+
+    Ok(webhook_form.events.join(","))
 }
 
+#[allow(clippy::single_match)]
 pub fn notify_error_handler<B>(
     res: actix_web::dev::ServiceResponse<B>,
 ) -> actix_web::Result<actix_web::middleware::ErrorHandlerResponse<B>> {
@@ -156,6 +832,12 @@ pub fn notify_error_handler<B>(
         ..Default::default()
     };
 
+    if let Some(request_id) = request.extensions().get::<middleware::RequestIdValue>() {
+        event
+            .tags
+            .insert("request_id".to_string(), request_id.0.clone());
+    }
+
     match res.response().error() {
         Some(err) => match err.as_error::<AppError>() {
             Some(err) => {
@@ -163,7 +845,7 @@ pub fn notify_error_handler<B>(
                 let backtrace = err.err.backtrace();
                 event.stacktrace =
                     sentry::integrations::backtrace::parse_stacktrace(&format!("{backtrace:#}"));
-                println!("AppError: {}", err.err);
+                tracing::error!(error = %err.err, "internal server error");
             }
             None => {}
         },
@@ -177,46 +859,53 @@ pub fn notify_error_handler<B>(
     ))
 }
 
-#[get("/")]
-async fn hello() -> impl Responder {
-    // panic!("Everything is on fire!");
-    // sentry::capture_message("Something is not well", sentry::Level::Warning);
-
-    // let uuid = sentry::types::Uuid::new_v4();
-    // let event = sentry::protocol::Event {
-    //     event_id: uuid,
-    //     message: Some("Hello World!".into()),
-    //     level: sentry::protocol::Level::Info,
-    //     ..Default::default()
-    // };
-
-    // sentry::capture_event(event.clone());
-
-    HttpResponse::Ok().body("Hello world!")
-}
-
+#[utoipa::path(
+    tag = "articles",
+    params(
+        ArticleIndexQuery
+    ),
+    responses(
+        (status = 200, description = "Articles with their tags", body = [ArticleIndexResponse]),
+        (status = 500, description = "Unexpected server error", body = HttpErrorResponse)
+    )
+)]
 #[get("/articles")]
-async fn articles_index(data: web::Data<super::AppState>) -> Result<HttpResponse, AppError> {
+async fn articles_index(
+    data: web::Data<super::AppState>,
+    query: web::Query<ArticleIndexQuery>,
+) -> Result<HttpResponse, AppError> {
+    let query = query.into_inner();
     let dtabase_connection = &data.database_connection;
 
-    let articles_repository = repository::ArticlesRepository::new(dtabase_connection.clone());
+    let articles_repository = repository::ArticlesRepository::cached(
+        dtabase_connection.clone(),
+        data.article_cache.clone(),
+    );
 
-    match articles_repository.find_all().await {
-        Ok(articles) => {
-            let response = articles
-                .iter()
-                .map(|article| ArticleIndexResponse {
-                    id: article.id,
-                    title: article.title.clone(),
-                    body: article.body.clone(),
-                })
-                .collect::<Vec<ArticleIndexResponse>>();
-            return Ok(HttpResponse::Ok().json(response));
+    let articles = match query.tag {
+        Some(tag) => {
+            articles_repository
+                .find_all_with_tags_by_tag_name(&tag)
+                .await
         }
+        None => articles_repository.find_all_with_tags().await,
+    };
+
+    match articles {
+        Ok(articles) => Ok(HttpResponse::Ok().json(article_index_responses(&articles))),
         Err(err) => Err(AppError::internal_server_error(err.into())),
     }
 }
 
+#[utoipa::path(
+    tag = "articles",
+    request_body = ArticleForm,
+    responses(
+        (status = 201, description = "Article created", body = ArticleShowResponse),
+        (status = 400, description = "Category does not exist", body = HttpErrorResponse),
+        (status = 500, description = "Unexpected server error", body = HttpErrorResponse)
+    )
+)]
 #[post("/articles")]
 async fn articles_create(
     data: web::Data<super::AppState>,
@@ -225,27 +914,76 @@ async fn articles_create(
     let article_form = article_form.into_inner();
     let dtabase_connection = &data.database_connection;
 
-    let articles_repository = repository::ArticlesRepository::new(dtabase_connection.clone());
+    let articles_repository = repository::ArticlesRepository::cached(
+        dtabase_connection.clone(),
+        data.article_cache.clone(),
+    );
+    let categories_repository = repository::CategoriesRepository::new(dtabase_connection.clone());
+
+    let category_id = match article_form.category_id {
+        Some(category_id) => {
+            validate_category_id(&categories_repository, category_id).await?;
+            category_id
+        }
+        None => match categories_repository.find_or_create_default().await {
+            Ok(category) => category.id,
+            Err(err) => return Err(AppError::internal_server_error(err.into())),
+        },
+    };
+    let author_email = match &article_form.author_email {
+        Some(author_email) => Some(validate_email("author_email", author_email)?),
+        None => None,
+    };
 
     let form = entity::articles::Model {
         id: 0,
         title: article_form.title,
         body: article_form.body,
+        category_id,
+        body_html: None,
+        created_at: Utc::now(),
+        updated_at: Utc::now(),
+        author_email,
     };
 
-    match articles_repository.create(form).await {
-        Ok(article) => {
+    match articles_repository
+        .create(form, &article_form.tags.unwrap_or_default())
+        .await
+    {
+        Ok((article, tags)) => {
+            data.metrics.article_created();
+            if let Ok(article) = article.clone().try_into_model() {
+                data.article_events.created(&article);
+                data.webhooks
+                    .article(webhook::WebhookEvent::ArticleCreated, &article);
+            }
+            let id = article.id.unwrap();
+
             let response = ArticleShowResponse {
-                id: article.id.unwrap(),
+                id,
                 title: article.title.unwrap(),
                 body: article.body.unwrap(),
+                body_html: article.body_html.unwrap().unwrap_or_default(),
+                category_id: article.category_id.unwrap(),
+                tags: tag_names(&tags),
             };
-            return Ok(HttpResponse::Created().json(response));
+            Ok(HttpResponse::Created().json(response))
         }
         Err(err) => Err(AppError::internal_server_error(err.into())),
     }
 }
 
+#[utoipa::path(
+    tag = "articles",
+    params(
+        ("id" = i32, Path, description = "Article ID")
+    ),
+    responses(
+        (status = 200, description = "Article", body = ArticleShowResponse),
+        (status = 404, description = "Article not found", body = HttpErrorResponse),
+        (status = 500, description = "Unexpected server error", body = HttpErrorResponse)
+    )
+)]
 #[get("/articles/{id}")]
 async fn articles_show(
     data: web::Data<super::AppState>,
@@ -254,24 +992,49 @@ async fn articles_show(
     let id = id.into_inner();
     let dtabase_connection = &data.database_connection;
 
-    let articles_repository = repository::ArticlesRepository::new(dtabase_connection.clone());
+    let articles_repository = repository::ArticlesRepository::cached(
+        dtabase_connection.clone(),
+        data.article_cache.clone(),
+    );
 
     match articles_repository.find_by_id(id).await {
         Ok(ok) => match ok {
-            Some(article) => {
-                let response = ArticleShowResponse {
-                    id: article.id,
-                    title: article.title,
-                    body: article.body,
-                };
-                Ok(HttpResponse::Ok().json(response))
-            }
+            Some(article) => match articles_repository.find_tags(&article).await {
+                Ok(tags) => {
+                    let body_html = article
+                        .body_html
+                        .unwrap_or_else(|| markdown::render_html(&article.body));
+                    let response = ArticleShowResponse {
+                        id: article.id,
+                        title: article.title,
+                        body: article.body,
+                        body_html,
+                        category_id: article.category_id,
+                        tags: tag_names(&tags),
+                    };
+                    Ok(HttpResponse::Ok().json(response))
+                }
+                Err(err) => Err(AppError::internal_server_error(err.into())),
+            },
             None => Err(AppError::not_found()),
         },
         Err(err) => Err(AppError::internal_server_error(err.into())),
     }
 }
 
+#[utoipa::path(
+    tag = "articles",
+    params(
+        ("id" = i32, Path, description = "Article ID")
+    ),
+    request_body = ArticleForm,
+    responses(
+        (status = 204, description = "Article updated"),
+        (status = 400, description = "Category does not exist", body = HttpErrorResponse),
+        (status = 404, description = "Article not found", body = HttpErrorResponse),
+        (status = 500, description = "Unexpected server error", body = HttpErrorResponse)
+    )
+)]
 #[patch("/articles/{id}")]
 async fn articles_update(
     data: web::Data<super::AppState>,
@@ -281,23 +1044,65 @@ async fn articles_update(
     let id = id.into_inner();
     let dtabase_connection = &data.database_connection;
 
-    let articles_repository = repository::ArticlesRepository::new(dtabase_connection.clone());
+    let articles_repository = repository::ArticlesRepository::cached(
+        dtabase_connection.clone(),
+        data.article_cache.clone(),
+    );
 
     match articles_repository.find_by_id(id).await {
         Ok(ok) => match ok {
-            Some(_) => {
+            Some(article) => {
                 let article_form = article_form.into_inner();
 
+                let categories_repository =
+                    repository::CategoriesRepository::new(dtabase_connection.clone());
+
+                let category_id = match article_form.category_id {
+                    Some(category_id) => {
+                        validate_category_id(&categories_repository, category_id).await?;
+                        category_id
+                    }
+                    None => article.category_id,
+                };
+                let author_email = match &article_form.author_email {
+                    Some(author_email) => Some(validate_email("author_email", author_email)?),
+                    None => article.author_email,
+                };
+
                 let form = entity::articles::Model {
                     id,
                     title: article_form.title,
                     body: article_form.body,
+                    category_id,
+                    body_html: None,
+                    created_at: Utc::now(),
+                    updated_at: Utc::now(),
+                    author_email,
                 };
 
-                match articles_repository.update(form).await {
-                    Ok(_) => Ok(HttpResponse::NoContent().body("")),
-                    Err(err) => Err(AppError::internal_server_error(err.into())),
+                let article = match articles_repository.update(form).await {
+                    Ok(article) => article,
+                    Err(err) => return Err(AppError::internal_server_error(err.into())),
+                };
+
+                if let Some(tags) = article_form.tags {
+                    let tags_repository = repository::TagsRepository::cached(
+                        dtabase_connection.clone(),
+                        data.article_cache.clone(),
+                    );
+
+                    if let Err(err) = tags_repository.replace_for_article(id, &tags).await {
+                        return Err(AppError::internal_server_error(err.into()));
+                    }
                 }
+
+                if let Ok(article) = article.try_into_model() {
+                    data.article_events.updated(&article);
+                    data.webhooks
+                        .article(webhook::WebhookEvent::ArticleUpdated, &article);
+                }
+
+                Ok(HttpResponse::NoContent().body(""))
             }
             None => Err(AppError::not_found()),
         },
@@ -305,6 +1110,17 @@ async fn articles_update(
     }
 }
 
+#[utoipa::path(
+    tag = "articles",
+    params(
+        ("id" = i32, Path, description = "Article ID")
+    ),
+    responses(
+        (status = 204, description = "Article deleted"),
+        (status = 404, description = "Article not found", body = HttpErrorResponse),
+        (status = 500, description = "Unexpected server error", body = HttpErrorResponse)
+    )
+)]
 #[delete("/articles/{id}")]
 async fn articles_delete(
     data: web::Data<super::AppState>,
@@ -313,12 +1129,21 @@ async fn articles_delete(
     let id = id.into_inner();
     let dtabase_connection = &data.database_connection;
 
-    let articles_repository = repository::ArticlesRepository::new(dtabase_connection.clone());
+    let articles_repository = repository::ArticlesRepository::cached(
+        dtabase_connection.clone(),
+        data.article_cache.clone(),
+    );
 
     match articles_repository.find_by_id(id).await {
         Ok(ok) => match ok {
-            Some(_) => match articles_repository.delete(id).await {
-                Ok(_) => Ok(HttpResponse::NoContent().body("")),
+            Some(article) => match articles_repository.delete(id).await {
+                Ok(_) => {
+                    data.article_events.deleted(id);
+                    data.webhooks
+                        .article(webhook::WebhookEvent::ArticleDeleted, &article);
+                    data.attachments.article_deleted(id);
+                    Ok(HttpResponse::NoContent().body(""))
+                }
                 Err(err) => Err(AppError::internal_server_error(err.into())),
             },
             None => Err(AppError::not_found()),
@@ -327,12 +1152,31 @@ async fn articles_delete(
     }
 }
 
+#[utoipa::path(
+    tag = "comments",
+    params(
+        ("article_id" = i32, Path, description = "Article ID"),
+        CommentIndexQuery
+    ),
+    responses(
+        (status = 200, description = "Approved comments, as a flat list or, with `format=tree`, as a tree of `CommentTreeResponse`", body = [CommentIndexResponse]),
+        (status = 400, description = "Unknown format", body = HttpErrorResponse),
+        (status = 404, description = "Article not found", body = HttpErrorResponse),
+        (status = 500, description = "Unexpected server error", body = HttpErrorResponse)
+    )
+)]
 #[get("/articles/{article_id}/comments")]
 async fn comments_index(
     data: web::Data<super::AppState>,
     path_info: web::Path<i32>,
+    query: web::Query<CommentIndexQuery>,
 ) -> Result<HttpResponse, AppError> {
     let article_id = path_info.into_inner();
+    let tree = match query.format.as_deref() {
+        None | Some("flat") => false,
+        Some("tree") => true,
+        Some(_) => return Err(AppError::bad_request("format must be flat or tree")),
+    };
     let dtabase_connection = &data.database_connection;
 
     let articles_repository = repository::ArticlesRepository::new(dtabase_connection.clone());
@@ -343,12 +1187,17 @@ async fn comments_index(
                 let comments_repository =
                     repository::CommentsRepository::new(dtabase_connection.clone());
 
-                match comments_repository.find_all_by_article_id(article_id).await {
+                match comments_repository
+                    .find_approved_by_article_id(article_id)
+                    .await
+                {
+                    Ok(comments) if tree => Ok(HttpResponse::Ok().json(comment_tree(&comments))),
                     Ok(comments) => {
                         let response = comments
                             .iter()
                             .map(|comment| CommentIndexResponse {
                                 id: comment.id,
+                                parent_id: comment.parent_id,
                                 body: comment.body.clone(),
                             })
                             .collect::<Vec<CommentIndexResponse>>();
@@ -363,6 +1212,20 @@ async fn comments_index(
     }
 }
 
+#[utoipa::path(
+    tag = "comments",
+    params(
+        ("article_id" = i32, Path, description = "Article ID")
+    ),
+    request_body = CommentForm,
+    responses(
+        (status = 201, description = "Comment created, possibly held for moderation", body = CommentShowResponse),
+        (status = 400, description = "Invalid parent comment", body = HttpErrorResponse),
+        (status = 404, description = "Article not found", body = HttpErrorResponse),
+        (status = 429, description = "Too many comments from this client", body = HttpErrorResponse),
+        (status = 500, description = "Unexpected server error", body = HttpErrorResponse)
+    )
+)]
 #[post("/articles/{article_id}/comments")]
 async fn comments_create(
     data: web::Data<super::AppState>,
@@ -381,17 +1244,65 @@ async fn comments_create(
                 let comments_repository =
                     repository::CommentsRepository::new(dtabase_connection.clone());
 
+                if let Some(parent_id) = comment_form.parent_id {
+                    let comments = match comments_repository
+                        .find_approved_by_article_id(article_id)
+                        .await
+                    {
+                        Ok(comments) => comments,
+                        Err(err) => return Err(AppError::internal_server_error(err.into())),
+                    };
+
+                    if !comments.iter().any(|comment| comment.id == parent_id) {
+                        return Err(AppError::bad_request(
+                            "parent_id must refer to a comment on the same article",
+                        ));
+                    }
+
+                    if comment_depth(&comments, parent_id) >= data.comment_max_depth {
+                        return Err(AppError::bad_request("replies are nested too deeply"));
+                    }
+                }
+
+                let candidate = spam::CommentCandidate {
+                    article_id,
+                    body: &comment_form.body,
+                };
+
+                let moderation_status = match data
+                    .spam_pipeline
+                    .initial_moderation_status(dtabase_connection, &candidate)
+                    .await
+                {
+                    Ok(moderation_status) => moderation_status,
+                    Err(err) => return Err(AppError::internal_server_error(err.into())),
+                };
+
                 let form = entity::comments::Model {
                     id: 0,
                     article_id,
                     body: comment_form.body,
+                    created_at: Utc::now(),
+                    updated_at: Utc::now(),
+                    parent_id: comment_form.parent_id,
+                    moderation_status,
+                    moderated_at: None,
                 };
 
                 match comments_repository.create(form).await {
                     Ok(comment) => {
+                        data.metrics.comment_created(moderation_status);
+                        if let Ok(comment) = comment.clone().try_into_model() {
+                            data.comment_hub.saved(&comment, None);
+                            data.comment_notifier.saved(&comment, None);
+                            data.webhooks
+                                .comment(webhook::WebhookEvent::CommentCreated, &comment);
+                        }
                         let response = CommentShowResponse {
                             id: comment.id.unwrap(),
+                            parent_id: comment.parent_id.unwrap(),
                             body: comment.body.unwrap(),
+                            moderation_status: comment.moderation_status.unwrap(),
                         };
                         Ok(HttpResponse::Created().json(response))
                     }
@@ -404,6 +1315,18 @@ async fn comments_create(
     }
 }
 
+#[utoipa::path(
+    tag = "comments",
+    params(
+        ("article_id" = i32, Path, description = "Article ID"),
+        ("id" = i32, Path, description = "Comment ID")
+    ),
+    responses(
+        (status = 200, description = "Approved comment", body = CommentShowResponse),
+        (status = 404, description = "Comment not found or not approved", body = HttpErrorResponse),
+        (status = 500, description = "Unexpected server error", body = HttpErrorResponse)
+    )
+)]
 #[get("/articles/{article_id}/comments/{id}")]
 async fn comments_show(
     data: web::Data<super::AppState>,
@@ -427,14 +1350,18 @@ async fn comments_show(
                     .await
                 {
                     Ok(comment) => match comment {
-                        Some(comment) => {
+                        Some(comment)
+                            if comment.moderation_status == ModerationStatus::Approved =>
+                        {
                             let response = CommentShowResponse {
                                 id: comment.id,
+                                parent_id: comment.parent_id,
                                 body: comment.body,
+                                moderation_status: comment.moderation_status,
                             };
                             Ok(HttpResponse::Ok().json(response))
                         }
-                        None => Err(AppError::not_found()),
+                        _ => Err(AppError::not_found()),
                     },
                     Err(err) => Err(AppError::internal_server_error(err.into())),
                 }
@@ -445,6 +1372,19 @@ async fn comments_show(
     }
 }
 
+#[utoipa::path(
+    tag = "comments",
+    params(
+        ("article_id" = i32, Path, description = "Article ID"),
+        ("id" = i32, Path, description = "Comment ID")
+    ),
+    request_body = CommentForm,
+    responses(
+        (status = 204, description = "Comment updated"),
+        (status = 404, description = "Comment not found", body = HttpErrorResponse),
+        (status = 500, description = "Unexpected server error", body = HttpErrorResponse)
+    )
+)]
 #[patch("/articles/{article_id}/comments/{id}")]
 async fn comments_update(
     data: web::Data<super::AppState>,
@@ -463,17 +1403,52 @@ async fn comments_update(
         .await
     {
         Ok(ok) => match ok {
-            Some(_) => {
+            Some(comment) => {
                 let comment_form = comment_form.into_inner();
 
+                let candidate = spam::CommentCandidate {
+                    article_id,
+                    body: &comment_form.body,
+                };
+
+                let moderation_status = match data
+                    .spam_pipeline
+                    .edited_moderation_status(
+                        dtabase_connection,
+                        &candidate,
+                        comment.moderation_status,
+                    )
+                    .await
+                {
+                    Ok(moderation_status) => moderation_status,
+                    Err(err) => return Err(AppError::internal_server_error(err.into())),
+                };
+
+                // A moderator's decision was about the old body.
                 let form = entity::comments::Model {
                     id,
                     article_id,
                     body: comment_form.body,
+                    created_at: Utc::now(),
+                    updated_at: Utc::now(),
+                    parent_id: comment.parent_id,
+                    moderation_status,
+                    moderated_at: None,
                 };
 
                 match comments_repository.update(form).await {
-                    Ok(_) => Ok(HttpResponse::NoContent().body("")),
+                    Ok(updated) => {
+                        data.spam_pipeline.forget(&comment);
+                        if let Ok(updated) = updated.try_into_model() {
+                            data.comment_hub
+                                .saved(&updated, Some(comment.moderation_status));
+                            data.comment_notifier
+                                .saved(&updated, Some(comment.moderation_status));
+                            data.webhooks
+                                .comment(webhook::WebhookEvent::CommentUpdated, &updated);
+                        }
+                        Ok(HttpResponse::NoContent().body(""))
+                    }
                     Err(err) => Err(AppError::internal_server_error(err.into())),
                 }
             }
@@ -483,6 +1458,18 @@ async fn comments_update(
     }
 }
 
+#[utoipa::path(
+    tag = "comments",
+    params(
+        ("article_id" = i32, Path, description = "Article ID"),
+        ("id" = i32, Path, description = "Comment ID")
+    ),
+    responses(
+        (status = 204, description = "Comment deleted"),
+        (status = 404, description = "Comment not found", body = HttpErrorResponse),
+        (status = 500, description = "Unexpected server error", body = HttpErrorResponse)
+    )
+)]
 #[delete("/articles/{article_id}/comments/{id}")]
 async fn comments_delete(
     data: web::Data<super::AppState>,
@@ -500,8 +1487,13 @@ async fn comments_delete(
         .await
     {
         Ok(ok) => match ok {
-            Some(_) => match comments_repository.delete(article_id, id).await {
-                Ok(_) => Ok(HttpResponse::NoContent().body("")),
+            Some(comment) => match comments_repository.delete(article_id, id).await {
+                Ok(_) => {
+                    data.comment_hub.deleted(&comment);
+                    data.webhooks
+                        .comment(webhook::WebhookEvent::CommentDeleted, &comment);
+                    Ok(HttpResponse::NoContent().body(""))
+                }
                 Err(err) => Err(AppError::internal_server_error(err.into())),
             },
             None => Err(AppError::not_found()),
@@ -509,3 +1501,1967 @@ async fn comments_delete(
         Err(err) => Err(AppError::internal_server_error(err.into())),
     }
 }
+
+#[utoipa::path(
+    tag = "comments",
+    params(
+        ("article_id" = i32, Path, description = "Article ID")
+    ),
+    request_body = CommentSubscriptionForm,
+    responses(
+        (status = 202, description = "Confirmation email queued; the subscription starts once its link is followed"),
+        (status = 400, description = "Invalid email address", body = HttpErrorResponse),
+        (status = 404, description = "Article not found", body = HttpErrorResponse),
+        (status = 500, description = "Unexpected server error", body = HttpErrorResponse),
+        (status = 503, description = "Email is not configured", body = HttpErrorResponse)
+    )
+)]
+#[post("/articles/{article_id}/subscriptions")]
+async fn comment_subscriptions_create(
+    data: web::Data<super::AppState>,
+    path_info: web::Path<i32>,
+    subscription_form: web::Json<CommentSubscriptionForm>,
+) -> Result<HttpResponse, AppError> {
+    let article_id = path_info.into_inner();
+    let database_connection = &data.database_connection;
+
+    let articles_repository = repository::ArticlesRepository::new(database_connection.clone());
+
+    let email = validate_email("email", &subscription_form.email)?;
+    if !data.comment_notifier.is_enabled() {
+        return Err(AppError::service_unavailable("email is not configured"));
+    }
+
+    match articles_repository.find_by_id(article_id).await {
+        Ok(ok) => match ok {
+            Some(_) => match data
+                .comment_notifier
+                .request_subscription(article_id, &email)
+                .await
+            {
+                Ok(_) => Ok(HttpResponse::Accepted().body("")),
+                Err(err) => Err(AppError::internal_server_error(err.into())),
+            },
+            None => Err(AppError::not_found()),
+        },
+        Err(err) => Err(AppError::internal_server_error(err.into())),
+    }
+}
+
+#[utoipa::path(
+    tag = "tags",
+    responses(
+        (status = 200, description = "Tags with the number of articles using them", body = [TagIndexResponse]),
+        (status = 500, description = "Unexpected server error", body = HttpErrorResponse)
+    )
+)]
+#[get("/tags")]
+async fn tags_index(data: web::Data<super::AppState>) -> Result<HttpResponse, AppError> {
+    let dtabase_connection = &data.database_connection;
+
+    let tags_repository = repository::TagsRepository::new(dtabase_connection.clone());
+
+    match tags_repository.find_all_with_article_count().await {
+        Ok(tags) => {
+            let response = tags
+                .into_iter()
+                .map(|tag| TagIndexResponse {
+                    id: tag.id,
+                    name: tag.name,
+                    article_count: tag.article_count,
+                })
+                .collect::<Vec<TagIndexResponse>>();
+            Ok(HttpResponse::Ok().json(response))
+        }
+        Err(err) => Err(AppError::internal_server_error(err.into())),
+    }
+}
+
+#[utoipa::path(
+    tag = "categories",
+    responses(
+        (status = 200, description = "Categories", body = [CategoryIndexResponse]),
+        (status = 500, description = "Unexpected server error", body = HttpErrorResponse)
+    )
+)]
+#[get("/categories")]
+async fn categories_index(data: web::Data<super::AppState>) -> Result<HttpResponse, AppError> {
+    let database_connection = &data.database_connection;
+
+    let categories_repository = repository::CategoriesRepository::new(database_connection.clone());
+
+    match categories_repository.find_all().await {
+        Ok(categories) => {
+            let response = categories
+                .into_iter()
+                .map(|category| CategoryIndexResponse {
+                    id: category.id,
+                    parent_id: category.parent_id,
+                    name: category.name,
+                })
+                .collect::<Vec<CategoryIndexResponse>>();
+            Ok(HttpResponse::Ok().json(response))
+        }
+        Err(err) => Err(AppError::internal_server_error(err.into())),
+    }
+}
+
+#[utoipa::path(
+    tag = "categories",
+    request_body = CategoryForm,
+    responses(
+        (status = 201, description = "Category created", body = CategoryShowResponse),
+        (status = 400, description = "Parent category does not exist", body = HttpErrorResponse),
+        (status = 500, description = "Unexpected server error", body = HttpErrorResponse)
+    )
+)]
+#[post("/categories")]
+async fn categories_create(
+    data: web::Data<super::AppState>,
+    category_form: web::Json<CategoryForm>,
+) -> Result<HttpResponse, AppError> {
+    let category_form = category_form.into_inner();
+    let database_connection = &data.database_connection;
+
+    let categories_repository = repository::CategoriesRepository::new(database_connection.clone());
+
+    if let Some(parent_id) = category_form.parent_id {
+        match categories_repository.find_by_id(parent_id).await {
+            Ok(Some(_)) => {}
+            Ok(None) => return Err(AppError::bad_request("parent_id does not exist")),
+            Err(err) => return Err(AppError::internal_server_error(err.into())),
+        }
+    }
+
+    let form = entity::categories::Model {
+        id: 0,
+        parent_id: category_form.parent_id,
+        name: category_form.name,
+    };
+
+    match categories_repository.create(form).await {
+        Ok(category) => {
+            let response = CategoryShowResponse {
+                id: category.id.unwrap(),
+                parent_id: category.parent_id.unwrap(),
+                name: category.name.unwrap(),
+            };
+            Ok(HttpResponse::Created().json(response))
+        }
+        Err(err) => Err(AppError::internal_server_error(err.into())),
+    }
+}
+
+#[utoipa::path(
+    tag = "categories",
+    params(
+        ("id" = i32, Path, description = "Category ID")
+    ),
+    responses(
+        (status = 200, description = "Category", body = CategoryShowResponse),
+        (status = 404, description = "Category not found", body = HttpErrorResponse),
+        (status = 500, description = "Unexpected server error", body = HttpErrorResponse)
+    )
+)]
+#[get("/categories/{id}")]
+async fn categories_show(
+    data: web::Data<super::AppState>,
+    id: web::Path<i32>,
+) -> Result<HttpResponse, AppError> {
+    let id = id.into_inner();
+    let database_connection = &data.database_connection;
+
+    let categories_repository = repository::CategoriesRepository::new(database_connection.clone());
+
+    match categories_repository.find_by_id(id).await {
+        Ok(ok) => match ok {
+            Some(category) => {
+                let response = CategoryShowResponse {
+                    id: category.id,
+                    parent_id: category.parent_id,
+                    name: category.name,
+                };
+                Ok(HttpResponse::Ok().json(response))
+            }
+            None => Err(AppError::not_found()),
+        },
+        Err(err) => Err(AppError::internal_server_error(err.into())),
+    }
+}
+
+#[utoipa::path(
+    tag = "categories",
+    params(
+        ("id" = i32, Path, description = "Category ID")
+    ),
+    request_body = CategoryForm,
+    responses(
+        (status = 204, description = "Category updated"),
+        (status = 400, description = "Invalid parent category", body = HttpErrorResponse),
+        (status = 404, description = "Category not found", body = HttpErrorResponse),
+        (status = 500, description = "Unexpected server error", body = HttpErrorResponse)
+    )
+)]
+#[patch("/categories/{id}")]
+async fn categories_update(
+    data: web::Data<super::AppState>,
+    id: web::Path<i32>,
+    category_form: web::Json<CategoryForm>,
+) -> Result<HttpResponse, AppError> {
+    let id = id.into_inner();
+    let database_connection = &data.database_connection;
+
+    let categories_repository = repository::CategoriesRepository::new(database_connection.clone());
+
+    match categories_repository.find_by_id(id).await {
+        Ok(ok) => match ok {
+            Some(_) => {
+                let category_form = category_form.into_inner();
+
+                if let Some(parent_id) = category_form.parent_id {
+                    match categories_repository.find_self_and_descendant_ids(id).await {
+                        Ok(ids) if ids.contains(&parent_id) => return Err(AppError::bad_request(
+                            "parent_id must not be the category itself or one of its descendants",
+                        )),
+                        Ok(_) => {}
+                        Err(err) => return Err(AppError::internal_server_error(err.into())),
+                    }
+
+                    match categories_repository.find_by_id(parent_id).await {
+                        Ok(Some(_)) => {}
+                        Ok(None) => return Err(AppError::bad_request("parent_id does not exist")),
+                        Err(err) => return Err(AppError::internal_server_error(err.into())),
+                    }
+                }
+
+                let form = entity::categories::Model {
+                    id,
+                    parent_id: category_form.parent_id,
+                    name: category_form.name,
+                };
+
+                match categories_repository.update(form).await {
+                    Ok(_) => Ok(HttpResponse::NoContent().body("")),
+                    Err(err) => Err(AppError::internal_server_error(err.into())),
+                }
+            }
+            None => Err(AppError::not_found()),
+        },
+        Err(err) => Err(AppError::internal_server_error(err.into())),
+    }
+}
+
+#[utoipa::path(
+    tag = "categories",
+    params(
+        ("id" = i32, Path, description = "Category ID")
+    ),
+    responses(
+        (status = 204, description = "Category deleted"),
+        (status = 404, description = "Category not found", body = HttpErrorResponse),
+        (status = 409, description = "Category still has articles or children", body = HttpErrorResponse),
+        (status = 500, description = "Unexpected server error", body = HttpErrorResponse)
+    )
+)]
+#[delete("/categories/{id}")]
+async fn categories_delete(
+    data: web::Data<super::AppState>,
+    id: web::Path<i32>,
+) -> Result<HttpResponse, AppError> {
+    let id = id.into_inner();
+    let database_connection = &data.database_connection;
+
+    let categories_repository = repository::CategoriesRepository::new(database_connection.clone());
+
+    match categories_repository.find_by_id(id).await {
+        Ok(ok) => match ok {
+            Some(_) => {
+                match categories_repository.count_articles(id).await {
+                    Ok(0) => {}
+                    Ok(_) => return Err(AppError::conflict("category still has articles")),
+                    Err(err) => return Err(AppError::internal_server_error(err.into())),
+                }
+
+                match categories_repository.count_children(id).await {
+                    Ok(0) => {}
+                    Ok(_) => return Err(AppError::conflict("category still has child categories")),
+                    Err(err) => return Err(AppError::internal_server_error(err.into())),
+                }
+
+                match categories_repository.delete(id).await {
+                    Ok(_) => Ok(HttpResponse::NoContent().body("")),
+                    Err(err) => Err(AppError::internal_server_error(err.into())),
+                }
+            }
+            None => Err(AppError::not_found()),
+        },
+        Err(err) => Err(AppError::internal_server_error(err.into())),
+    }
+}
+
+#[utoipa::path(
+    tag = "categories",
+    params(
+        ("id" = i32, Path, description = "Category ID"),
+        CategoryArticlesQuery
+    ),
+    responses(
+        (status = 200, description = "Articles in the category", body = [ArticleIndexResponse]),
+        (status = 404, description = "Category not found", body = HttpErrorResponse),
+        (status = 500, description = "Unexpected server error", body = HttpErrorResponse)
+    )
+)]
+#[get("/categories/{id}/articles")]
+async fn categories_articles_index(
+    data: web::Data<super::AppState>,
+    id: web::Path<i32>,
+    query: web::Query<CategoryArticlesQuery>,
+) -> Result<HttpResponse, AppError> {
+    let id = id.into_inner();
+    let database_connection = &data.database_connection;
+
+    let categories_repository = repository::CategoriesRepository::new(database_connection.clone());
+    let articles_repository = repository::ArticlesRepository::new(database_connection.clone());
+
+    match categories_repository.find_by_id(id).await {
+        Ok(ok) => match ok {
+            Some(_) => {
+                let category_ids = if query.include_descendants.unwrap_or(false) {
+                    match categories_repository.find_self_and_descendant_ids(id).await {
+                        Ok(ids) => ids,
+                        Err(err) => return Err(AppError::internal_server_error(err.into())),
+                    }
+                } else {
+                    vec![id]
+                };
+
+                match articles_repository
+                    .find_all_with_tags_by_category_ids(category_ids)
+                    .await
+                {
+                    Ok(articles) => Ok(HttpResponse::Ok().json(article_index_responses(&articles))),
+                    Err(err) => Err(AppError::internal_server_error(err.into())),
+                }
+            }
+            None => Err(AppError::not_found()),
+        },
+        Err(err) => Err(AppError::internal_server_error(err.into())),
+    }
+}
+
+#[utoipa::path(
+    tag = "feeds",
+    responses(
+        (status = 200, description = "RSS 2.0 feed of the latest articles", content_type = "application/rss+xml", body = String),
+        (status = 304, description = "Feed unchanged since `If-None-Match`"),
+        (status = 500, description = "Unexpected server error", body = HttpErrorResponse)
+    )
+)]
+#[get("/feed.rss")]
+async fn feed_rss(
+    request: HttpRequest,
+    data: web::Data<super::AppState>,
+) -> Result<HttpResponse, AppError> {
+    let database_connection = &data.database_connection;
+
+    let articles_repository = repository::ArticlesRepository::new(database_connection.clone());
+
+    match articles_repository.find_latest(feed::ENTRY_LIMIT).await {
+        Ok(articles) => Ok(xml_response(
+            &request,
+            feed::RSS_CONTENT_TYPE,
+            feed::articles_rss(&data.base_url, &articles),
+        )),
+        Err(err) => Err(AppError::internal_server_error(err.into())),
+    }
+}
+
+#[utoipa::path(
+    tag = "feeds",
+    responses(
+        (status = 200, description = "Atom feed of the latest articles", content_type = "application/atom+xml", body = String),
+        (status = 304, description = "Feed unchanged since `If-None-Match`"),
+        (status = 500, description = "Unexpected server error", body = HttpErrorResponse)
+    )
+)]
+#[get("/feed.atom")]
+async fn feed_atom(
+    request: HttpRequest,
+    data: web::Data<super::AppState>,
+) -> Result<HttpResponse, AppError> {
+    let database_connection = &data.database_connection;
+
+    let articles_repository = repository::ArticlesRepository::new(database_connection.clone());
+
+    match articles_repository.find_latest(feed::ENTRY_LIMIT).await {
+        Ok(articles) => Ok(xml_response(
+            &request,
+            feed::ATOM_CONTENT_TYPE,
+            feed::articles_atom(&data.base_url, &articles),
+        )),
+        Err(err) => Err(AppError::internal_server_error(err.into())),
+    }
+}
+
+#[utoipa::path(
+    tag = "feeds",
+    params(
+        ("article_id" = i32, Path, description = "Article ID")
+    ),
+    responses(
+        (status = 200, description = "Atom feed of the latest approved comments on an article", content_type = "application/atom+xml", body = String),
+        (status = 304, description = "Feed unchanged since `If-None-Match`"),
+        (status = 404, description = "Article not found", body = HttpErrorResponse),
+        (status = 500, description = "Unexpected server error", body = HttpErrorResponse)
+    )
+)]
+#[get("/articles/{article_id}/comments.atom")]
+async fn comments_feed_atom(
+    request: HttpRequest,
+    data: web::Data<super::AppState>,
+    path_info: web::Path<i32>,
+) -> Result<HttpResponse, AppError> {
+    let article_id = path_info.into_inner();
+    let database_connection = &data.database_connection;
+
+    let articles_repository = repository::ArticlesRepository::new(database_connection.clone());
+
+    match articles_repository.find_by_id(article_id).await {
+        Ok(ok) => match ok {
+            Some(article) => {
+                let comments_repository =
+                    repository::CommentsRepository::new(database_connection.clone());
+
+                match comments_repository
+                    .find_latest_approved_by_article_id(article_id, feed::ENTRY_LIMIT)
+                    .await
+                {
+                    Ok(comments) => Ok(xml_response(
+                        &request,
+                        feed::ATOM_CONTENT_TYPE,
+                        feed::comments_atom(&data.base_url, &article, &comments),
+                    )),
+                    Err(err) => Err(AppError::internal_server_error(err.into())),
+                }
+            }
+            None => Err(AppError::not_found()),
+        },
+        Err(err) => Err(AppError::internal_server_error(err.into())),
+    }
+}
+
+#[utoipa::path(
+    tag = "sitemaps",
+    responses(
+        (status = 200, description = "Sitemap, or a sitemap index once there are too many articles for one", content_type = "application/xml", body = String),
+        (status = 500, description = "Unexpected server error", body = HttpErrorResponse)
+    )
+)]
+#[get("/sitemap.xml")]
+async fn sitemap_index(data: web::Data<super::AppState>) -> Result<HttpResponse, AppError> {
+    let database_connection = &data.database_connection;
+
+    let articles_repository = repository::ArticlesRepository::new(database_connection.clone());
+
+    match articles_repository.count().await {
+        Ok(count) if count > sitemap::MAX_URLS => Ok(HttpResponse::Ok()
+            .insert_header((header::CONTENT_TYPE, sitemap::CONTENT_TYPE))
+            .body(sitemap::index(&data.base_url, sitemap::page_count(count)))),
+        Ok(_) => Ok(HttpResponse::Ok()
+            .insert_header((header::CONTENT_TYPE, sitemap::CONTENT_TYPE))
+            .streaming(sitemap::urlset(
+                database_connection.clone(),
+                data.base_url.clone(),
+                1,
+            ))),
+        Err(err) => Err(AppError::internal_server_error(err.into())),
+    }
+}
+
+#[utoipa::path(
+    tag = "sitemaps",
+    params(
+        ("page" = u64, Path, description = "Sitemap page, starting at 1")
+    ),
+    responses(
+        (status = 200, description = "One page of the sitemap", content_type = "application/xml", body = String),
+        (status = 404, description = "Page out of range", body = HttpErrorResponse),
+        (status = 500, description = "Unexpected server error", body = HttpErrorResponse)
+    )
+)]
+#[get("/sitemaps/{page}.xml")]
+async fn sitemap_page(
+    data: web::Data<super::AppState>,
+    page: web::Path<u64>,
+) -> Result<HttpResponse, AppError> {
+    let page = page.into_inner();
+    let database_connection = &data.database_connection;
+
+    let articles_repository = repository::ArticlesRepository::new(database_connection.clone());
+
+    match articles_repository.count().await {
+        Ok(count) if page >= 1 && page <= sitemap::page_count(count) => Ok(HttpResponse::Ok()
+            .insert_header((header::CONTENT_TYPE, sitemap::CONTENT_TYPE))
+            .streaming(sitemap::urlset(
+                database_connection.clone(),
+                data.base_url.clone(),
+                page,
+            ))),
+        Ok(_) => Err(AppError::not_found()),
+        Err(err) => Err(AppError::internal_server_error(err.into())),
+    }
+}
+
+#[utoipa::path(
+    tag = "revisions",
+    params(
+        ("id" = i32, Path, description = "Article ID")
+    ),
+    responses(
+        (status = 200, description = "Revisions of the article, newest first", body = [ArticleRevisionIndexResponse]),
+        (status = 404, description = "Article not found", body = HttpErrorResponse),
+        (status = 500, description = "Unexpected server error", body = HttpErrorResponse)
+    )
+)]
+#[get("/articles/{id}/revisions")]
+async fn article_revisions_index(
+    data: web::Data<super::AppState>,
+    id: web::Path<i32>,
+) -> Result<HttpResponse, AppError> {
+    let id = id.into_inner();
+    let database_connection = &data.database_connection;
+
+    let articles_repository = repository::ArticlesRepository::new(database_connection.clone());
+
+    match articles_repository.find_by_id(id).await {
+        Ok(ok) => match ok {
+            Some(_) => {
+                let article_revisions_repository =
+                    repository::ArticleRevisionsRepository::new(database_connection.clone());
+
+                match article_revisions_repository
+                    .find_all_by_article_id(id)
+                    .await
+                {
+                    Ok(revisions) => {
+                        let response = revisions
+                            .into_iter()
+                            .map(|revision| ArticleRevisionIndexResponse {
+                                revision: revision.revision,
+                                title: revision.title,
+                                created_at: revision.created_at,
+                            })
+                            .collect::<Vec<ArticleRevisionIndexResponse>>();
+                        Ok(HttpResponse::Ok().json(response))
+                    }
+                    Err(err) => Err(AppError::internal_server_error(err.into())),
+                }
+            }
+            None => Err(AppError::not_found()),
+        },
+        Err(err) => Err(AppError::internal_server_error(err.into())),
+    }
+}
+
+#[utoipa::path(
+    tag = "revisions",
+    params(
+        ("id" = i32, Path, description = "Article ID"),
+        ("revision" = i32, Path, description = "Revision number"),
+        ArticleRevisionDiffQuery
+    ),
+    responses(
+        (status = 200, description = "Unified diff between two revisions", body = ArticleRevisionDiffResponse),
+        (status = 400, description = "Invalid revision to compare against", body = HttpErrorResponse),
+        (status = 404, description = "Article or revision not found", body = HttpErrorResponse),
+        (status = 500, description = "Unexpected server error", body = HttpErrorResponse)
+    )
+)]
+#[get("/articles/{id}/revisions/{revision}/diff")]
+async fn article_revisions_diff(
+    data: web::Data<super::AppState>,
+    path_info: web::Path<(i32, i32)>,
+    query: web::Query<ArticleRevisionDiffQuery>,
+) -> Result<HttpResponse, AppError> {
+    let (id, revision) = path_info.into_inner();
+    let database_connection = &data.database_connection;
+
+    let article_revisions_repository =
+        repository::ArticleRevisionsRepository::new(database_connection.clone());
+
+    let revision = match article_revisions_repository
+        .find_by_article_id_and_revision(id, revision)
+        .await
+    {
+        Ok(Some(revision)) => revision,
+        Ok(None) => return Err(AppError::not_found()),
+        Err(err) => return Err(AppError::internal_server_error(err.into())),
+    };
+
+    let against = match query.against {
+        Some(against) => match article_revisions_repository
+            .find_by_article_id_and_revision(id, against)
+            .await
+        {
+            Ok(Some(against)) => Some(against),
+            Ok(None) => return Err(AppError::bad_request("against revision does not exist")),
+            Err(err) => return Err(AppError::internal_server_error(err.into())),
+        },
+        None => match article_revisions_repository
+            .find_by_article_id_and_revision(id, revision.revision - 1)
+            .await
+        {
+            Ok(against) => against,
+            Err(err) => return Err(AppError::internal_server_error(err.into())),
+        },
+    };
+
+    let response = ArticleRevisionDiffResponse {
+        revision: revision.revision,
+        against: against.as_ref().map(|against| against.revision),
+        diff: revision_diff(against.as_ref(), &revision),
+    };
+    Ok(HttpResponse::Ok().json(response))
+}
+
+#[utoipa::path(
+    tag = "revisions",
+    params(
+        ("id" = i32, Path, description = "Article ID"),
+        ("revision" = i32, Path, description = "Revision number")
+    ),
+    responses(
+        (status = 204, description = "Revision restored as a new revision"),
+        (status = 404, description = "Article or revision not found", body = HttpErrorResponse),
+        (status = 500, description = "Unexpected server error", body = HttpErrorResponse)
+    )
+)]
+#[post("/articles/{id}/revisions/{revision}/restore")]
+async fn article_revisions_restore(
+    data: web::Data<super::AppState>,
+    path_info: web::Path<(i32, i32)>,
+) -> Result<HttpResponse, AppError> {
+    let (id, revision) = path_info.into_inner();
+    let database_connection = &data.database_connection;
+
+    let articles_repository = repository::ArticlesRepository::cached(
+        database_connection.clone(),
+        data.article_cache.clone(),
+    );
+    let article_revisions_repository =
+        repository::ArticleRevisionsRepository::new(database_connection.clone());
+
+    let article = match articles_repository.find_by_id(id).await {
+        Ok(Some(article)) => article,
+        Ok(None) => return Err(AppError::not_found()),
+        Err(err) => return Err(AppError::internal_server_error(err.into())),
+    };
+
+    match article_revisions_repository
+        .find_by_article_id_and_revision(id, revision)
+        .await
+    {
+        Ok(ok) => match ok {
+            Some(revision) => {
+                let form = entity::articles::Model {
+                    title: revision.title,
+                    body: revision.body,
+                    ..article
+                };
+
+                match articles_repository.update(form).await {
+                    Ok(article) => {
+                        if let Ok(article) = article.try_into_model() {
+                            data.article_events.updated(&article);
+                            data.webhooks
+                                .article(webhook::WebhookEvent::ArticleUpdated, &article);
+                        }
+                        Ok(HttpResponse::NoContent().body(""))
+                    }
+                    Err(err) => Err(AppError::internal_server_error(err.into())),
+                }
+            }
+            None => Err(AppError::not_found()),
+        },
+        Err(err) => Err(AppError::internal_server_error(err.into())),
+    }
+}
+
+/// A file read from an upload, checked against the attachment limits.
+struct Upload {
+    file_name: String,
+    content_type: mime::Mime,
+    data: web::Bytes,
+    /// Pixel dimensions of images.
+    dimensions: Option<(u32, u32)>,
+}
+
+/// Reads the file in `field`, or returns `None` for a field that is not a
+/// file. Images are checked to be what they claim and have their EXIF
+/// metadata stripped.
+async fn read_upload(
+    attachments: &attachment::Attachments,
+    mut field: Field,
+) -> Result<Option<Upload>, AppError> {
+    let file_name = match field
+        .content_disposition()
+        .and_then(|content_disposition| content_disposition.get_filename())
+    {
+        Some(file_name) => attachment::sanitize_file_name(file_name),
+        None => return Ok(None),
+    };
+
+    let content_type = match field.content_type() {
+        Some(content_type) if attachments.allows(content_type) => content_type.clone(),
+        Some(content_type) => {
+            return Err(AppError::unsupported_media_type(&format!(
+                "{file_name}: {} files are not allowed",
+                content_type.essence_str()
+            )))
+        }
+        None => {
+            return Err(AppError::unsupported_media_type(&format!(
+                "{file_name}: missing content type"
+            )))
+        }
+    };
+
+    let mut data = web::BytesMut::new();
+    while let Some(chunk) = field.next().await {
+        let chunk = match chunk {
+            Ok(chunk) => chunk,
+            Err(err) => return Err(AppError::bad_request(&err.to_string())),
+        };
+        if data.len() + chunk.len() > attachments.max_size() {
+            return Err(AppError::payload_too_large(&format!(
+                "{file_name}: files must not be larger than {} bytes",
+                attachments.max_size()
+            )));
+        }
+        data.extend_from_slice(&chunk);
+    }
+    let mut data = data.freeze();
+
+    let mut dimensions = None;
+    if imaging::is_processable(&content_type) {
+        let normalized = {
+            let data = data.clone();
+            let content_type = content_type.clone();
+            match web::block(move || imaging::normalize(&data, &content_type)).await {
+                Ok(normalized) => normalized,
+                Err(err) => return Err(AppError::internal_server_error(err.into())),
+            }
+        };
+        let normalized = match normalized {
+            Ok(normalized) => normalized,
+            Err(_) => {
+                return Err(AppError::unsupported_media_type(&format!(
+                    "{file_name}: not a valid {} image",
+                    content_type.essence_str()
+                )))
+            }
+        };
+        if let Some(stripped) = normalized.data {
+            data = stripped.into();
+        }
+        dimensions = Some((normalized.width, normalized.height));
+    }
+
+    Ok(Some(Upload {
+        file_name,
+        content_type,
+        data,
+        dimensions,
+    }))
+}
+
+#[utoipa::path(
+    tag = "attachments",
+    params(
+        ("id" = i32, Path, description = "Article ID")
+    ),
+    responses(
+        (status = 200, description = "Files attached to the article", body = [AttachmentResponse]),
+        (status = 404, description = "Article not found", body = HttpErrorResponse),
+        (status = 500, description = "Unexpected server error", body = HttpErrorResponse)
+    )
+)]
+#[get("/articles/{id}/attachments")]
+async fn attachments_index(
+    data: web::Data<super::AppState>,
+    id: web::Path<i32>,
+) -> Result<HttpResponse, AppError> {
+    let id = id.into_inner();
+    let database_connection = &data.database_connection;
+
+    let articles_repository = repository::ArticlesRepository::new(database_connection.clone());
+    let attachments_repository =
+        repository::AttachmentsRepository::new(database_connection.clone());
+    let attachment_variants_repository =
+        repository::AttachmentVariantsRepository::new(database_connection.clone());
+
+    match articles_repository.find_by_id(id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return Err(AppError::not_found()),
+        Err(err) => return Err(AppError::internal_server_error(err.into())),
+    }
+
+    let attachments = match attachments_repository.find_by_article_id(id).await {
+        Ok(attachments) => attachments,
+        Err(err) => return Err(AppError::internal_server_error(err.into())),
+    };
+    let mut variants = match attachment_variants_repository
+        .find_by_attachment_ids(attachments.iter().map(|attachment| attachment.id).collect())
+        .await
+    {
+        Ok(variants) => variants.into_iter().fold(
+            HashMap::<i32, Vec<entity::attachment_variants::Model>>::new(),
+            |mut variants, variant| {
+                variants
+                    .entry(variant.attachment_id)
+                    .or_default()
+                    .push(variant);
+                variants
+            },
+        ),
+        Err(err) => return Err(AppError::internal_server_error(err.into())),
+    };
+
+    Ok(HttpResponse::Ok().json(
+        attachments
+            .into_iter()
+            .map(|attachment| {
+                let variants = variants.remove(&attachment.id).unwrap_or_default();
+                AttachmentResponse::new(attachment, variants)
+            })
+            .collect::<Vec<AttachmentResponse>>(),
+    ))
+}
+
+/// Every file is checked before any is stored, and the files are recorded in
+/// one transaction, so that a rejected or failed upload leaves nothing
+/// behind.
+#[utoipa::path(
+    tag = "attachments",
+    params(
+        ("id" = i32, Path, description = "Article ID")
+    ),
+    request_body(content = AttachmentUploadForm, content_type = "multipart/form-data"),
+    responses(
+        (status = 201, description = "Files attached", body = [AttachmentResponse]),
+        (status = 400, description = "Malformed upload, or no files in it", body = HttpErrorResponse),
+        (status = 404, description = "Article not found", body = HttpErrorResponse),
+        (status = 413, description = "A file is too large", body = HttpErrorResponse),
+        (status = 415, description = "A file is of a type that is not allowed, or not the image it claims to be", body = HttpErrorResponse),
+        (status = 500, description = "Unexpected server error", body = HttpErrorResponse)
+    )
+)]
+#[post("/articles/{id}/attachments")]
+async fn attachments_create(
+    data: web::Data<super::AppState>,
+    id: web::Path<i32>,
+    mut payload: Multipart,
+) -> Result<HttpResponse, AppError> {
+    let id = id.into_inner();
+    let database_connection = &data.database_connection;
+
+    let articles_repository = repository::ArticlesRepository::new(database_connection.clone());
+    let attachments_repository =
+        repository::AttachmentsRepository::new(database_connection.clone());
+
+    match articles_repository.find_by_id(id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return Err(AppError::not_found()),
+        Err(err) => return Err(AppError::internal_server_error(err.into())),
+    }
+
+    let mut uploads = Vec::new();
+    while let Some(field) = payload.next().await {
+        let field = match field {
+            Ok(field) => field,
+            Err(err) => return Err(AppError::bad_request(&err.to_string())),
+        };
+        if let Some(upload) = read_upload(&data.attachments, field).await? {
+            if uploads.len() == attachment::MAX_FILES_PER_REQUEST {
+                return Err(AppError::bad_request(&format!(
+                    "at most {} files can be uploaded at once",
+                    attachment::MAX_FILES_PER_REQUEST
+                )));
+            }
+            uploads.push(upload);
+        }
+    }
+    if uploads.is_empty() {
+        return Err(AppError::bad_request("no files were uploaded"));
+    }
+
+    let mut forms: Vec<entity::attachments::Model> = Vec::with_capacity(uploads.len());
+    for upload in uploads {
+        let storage_key = attachment::storage_key(id, &upload.content_type);
+        let size = upload.data.len() as i64;
+
+        if let Err(err) = data
+            .attachments
+            .storage()
+            .put(&storage_key, upload.data)
+            .await
+        {
+            for form in forms.iter() {
+                data.attachments.deleted(form);
+            }
+            return Err(AppError::internal_server_error(err.into()));
+        }
+
+        forms.push(entity::attachments::Model {
+            id: 0,
+            article_id: id,
+            storage_key,
+            file_name: upload.file_name,
+            content_type: upload.content_type.essence_str().to_string(),
+            size,
+            created_at: Utc::now(),
+            width: upload.dimensions.map(|(width, _)| width as i32),
+            height: upload.dimensions.map(|(_, height)| height as i32),
+        });
+    }
+
+    let attachments = match attachments_repository.create_many(forms.clone()).await {
+        Ok(attachments) => attachments,
+        Err(err) => {
+            for form in forms.iter() {
+                data.attachments.deleted(form);
+            }
+            return Err(AppError::internal_server_error(err.into()));
+        }
+    };
+
+    let mut responses = Vec::with_capacity(attachments.len());
+    for attachment in attachments {
+        data.attachments.created(&attachment);
+        responses.push(AttachmentResponse::new(attachment, Vec::new()));
+    }
+
+    Ok(HttpResponse::Created().json(responses))
+}
+
+/// The attachment's details, including its variants once they are made.
+#[utoipa::path(
+    tag = "attachments",
+    params(
+        ("article_id" = i32, Path, description = "Article ID"),
+        ("id" = i32, Path, description = "Attachment ID")
+    ),
+    responses(
+        (status = 200, description = "Attachment found", body = AttachmentResponse),
+        (status = 404, description = "Attachment not found", body = HttpErrorResponse),
+        (status = 500, description = "Unexpected server error", body = HttpErrorResponse)
+    )
+)]
+#[get("/articles/{article_id}/attachments/{id}")]
+async fn article_attachments_show(
+    data: web::Data<super::AppState>,
+    path_info: web::Path<(i32, i32)>,
+) -> Result<HttpResponse, AppError> {
+    let (article_id, id) = path_info.into_inner();
+    let database_connection = &data.database_connection;
+
+    let attachments_repository =
+        repository::AttachmentsRepository::new(database_connection.clone());
+    let attachment_variants_repository =
+        repository::AttachmentVariantsRepository::new(database_connection.clone());
+
+    match attachments_repository
+        .find_by_article_id_and_id(article_id, id)
+        .await
+    {
+        Ok(ok) => match ok {
+            Some(attachment) => match attachment_variants_repository
+                .find_by_attachment_id(id)
+                .await
+            {
+                Ok(variants) => {
+                    Ok(HttpResponse::Ok().json(AttachmentResponse::new(attachment, variants)))
+                }
+                Err(err) => Err(AppError::internal_server_error(err.into())),
+            },
+            None => Err(AppError::not_found()),
+        },
+        Err(err) => Err(AppError::internal_server_error(err.into())),
+    }
+}
+
+#[utoipa::path(
+    tag = "attachments",
+    params(
+        ("article_id" = i32, Path, description = "Article ID"),
+        ("id" = i32, Path, description = "Attachment ID")
+    ),
+    responses(
+        (status = 204, description = "Attachment deleted"),
+        (status = 404, description = "Attachment not found", body = HttpErrorResponse),
+        (status = 500, description = "Unexpected server error", body = HttpErrorResponse)
+    )
+)]
+#[delete("/articles/{article_id}/attachments/{id}")]
+async fn attachments_delete(
+    data: web::Data<super::AppState>,
+    path_info: web::Path<(i32, i32)>,
+) -> Result<HttpResponse, AppError> {
+    let (article_id, id) = path_info.into_inner();
+    let database_connection = &data.database_connection;
+
+    let attachments_repository =
+        repository::AttachmentsRepository::new(database_connection.clone());
+
+    match attachments_repository
+        .find_by_article_id_and_id(article_id, id)
+        .await
+    {
+        Ok(ok) => match ok {
+            Some(attachment) => match attachments_repository.delete(id).await {
+                Ok(_) => {
+                    data.attachments.deleted(&attachment);
+                    Ok(HttpResponse::NoContent().body(""))
+                }
+                Err(err) => Err(AppError::internal_server_error(err.into())),
+            },
+            None => Err(AppError::not_found()),
+        },
+        Err(err) => Err(AppError::internal_server_error(err.into())),
+    }
+}
+
+/// Serves the file itself, with `Range`, `If-None-Match` and
+/// `If-Modified-Since` support. Images are shown inline, anything else is
+/// downloaded.
+#[utoipa::path(
+    tag = "attachments",
+    params(
+        ("id" = i32, Path, description = "Attachment ID")
+    ),
+    responses(
+        (status = 200, description = "File contents"),
+        (status = 206, description = "Requested range of the file contents"),
+        (status = 304, description = "File unchanged"),
+        (status = 404, description = "Attachment not found", body = HttpErrorResponse),
+        (status = 416, description = "Requested range not satisfiable"),
+        (status = 500, description = "Unexpected server error", body = HttpErrorResponse)
+    )
+)]
+#[get("/attachments/{id}")]
+async fn attachments_show(
+    request: HttpRequest,
+    data: web::Data<super::AppState>,
+    id: web::Path<i32>,
+) -> Result<HttpResponse, AppError> {
+    let id = id.into_inner();
+    let database_connection = &data.database_connection;
+
+    let attachments_repository =
+        repository::AttachmentsRepository::new(database_connection.clone());
+
+    let attachment = match attachments_repository.find_by_id(id).await {
+        Ok(Some(attachment)) => attachment,
+        Ok(None) => return Err(AppError::not_found()),
+        Err(err) => return Err(AppError::internal_server_error(err.into())),
+    };
+
+    serve_attachment_file(
+        &request,
+        &data.attachments,
+        &attachment.storage_key,
+        &attachment.content_type,
+        &attachment.file_name,
+    )
+    .await
+}
+
+/// Serves one of the resized copies of an image attachment, like the original
+/// but named after its width.
+#[utoipa::path(
+    tag = "attachments",
+    params(
+        ("attachment_id" = i32, Path, description = "Attachment ID"),
+        ("id" = i32, Path, description = "Variant ID")
+    ),
+    responses(
+        (status = 200, description = "Variant contents"),
+        (status = 206, description = "Requested range of the variant contents"),
+        (status = 304, description = "Variant unchanged"),
+        (status = 404, description = "Variant not found", body = HttpErrorResponse),
+        (status = 416, description = "Requested range not satisfiable"),
+        (status = 500, description = "Unexpected server error", body = HttpErrorResponse)
+    )
+)]
+#[get("/attachments/{attachment_id}/variants/{id}")]
+async fn attachment_variants_show(
+    request: HttpRequest,
+    data: web::Data<super::AppState>,
+    path_info: web::Path<(i32, i32)>,
+) -> Result<HttpResponse, AppError> {
+    let (attachment_id, id) = path_info.into_inner();
+    let database_connection = &data.database_connection;
+
+    let attachments_repository =
+        repository::AttachmentsRepository::new(database_connection.clone());
+    let attachment_variants_repository =
+        repository::AttachmentVariantsRepository::new(database_connection.clone());
+
+    let attachment = match attachments_repository.find_by_id(attachment_id).await {
+        Ok(Some(attachment)) => attachment,
+        Ok(None) => return Err(AppError::not_found()),
+        Err(err) => return Err(AppError::internal_server_error(err.into())),
+    };
+    let variant = match attachment_variants_repository
+        .find_by_attachment_id_and_id(attachment_id, id)
+        .await
+    {
+        Ok(Some(variant)) => variant,
+        Ok(None) => return Err(AppError::not_found()),
+        Err(err) => return Err(AppError::internal_server_error(err.into())),
+    };
+
+    let stem = attachment
+        .file_name
+        .rsplit_once('.')
+        .map_or(attachment.file_name.as_str(), |(stem, _)| stem);
+    let extension = variant
+        .storage_key
+        .rsplit_once('.')
+        .map_or("bin", |(_, extension)| extension);
+
+    serve_attachment_file(
+        &request,
+        &data.attachments,
+        &variant.storage_key,
+        &variant.content_type,
+        &format!("{stem}-{}w.{extension}", variant.width),
+    )
+    .await
+}
+
+async fn serve_attachment_file(
+    request: &HttpRequest,
+    attachments: &attachment::Attachments,
+    storage_key: &str,
+    content_type: &str,
+    file_name: &str,
+) -> Result<HttpResponse, AppError> {
+    let content_type = content_type
+        .parse::<mime::Mime>()
+        .unwrap_or(mime::APPLICATION_OCTET_STREAM);
+    let inline = content_type.type_() == mime::IMAGE;
+
+    match attachments
+        .storage()
+        .respond(
+            storage_key,
+            content_type,
+            storage::content_disposition(inline, file_name),
+            request,
+        )
+        .await
+    {
+        Ok(mut response) => {
+            response.headers_mut().insert(
+                header::X_CONTENT_TYPE_OPTIONS,
+                header::HeaderValue::from_static("nosniff"),
+            );
+            Ok(response)
+        }
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Err(AppError::not_found()),
+        Err(err) => Err(AppError::internal_server_error(err.into())),
+    }
+}
+
+async fn moderate_comment(
+    data: web::Data<super::AppState>,
+    id: i32,
+    moderation_status: ModerationStatus,
+) -> Result<HttpResponse, AppError> {
+    let database_connection = &data.database_connection;
+
+    let comments_repository = repository::CommentsRepository::new(database_connection.clone());
+
+    match comments_repository.find_by_id(id).await {
+        Ok(ok) => match ok {
+            Some(comment) => match comments_repository
+                .update_moderation_status(id, moderation_status)
+                .await
+            {
+                Ok(updated) => {
+                    data.spam_pipeline.learn(
+                        &comment.body,
+                        comment.moderated_at.map(|_| comment.moderation_status),
+                        moderation_status,
+                    );
+                    if let Ok(updated) = updated.try_into_model() {
+                        data.comment_hub
+                            .saved(&updated, Some(comment.moderation_status));
+                        data.comment_notifier
+                            .saved(&updated, Some(comment.moderation_status));
+                        data.webhooks
+                            .comment(webhook::WebhookEvent::CommentUpdated, &updated);
+                    }
+                    Ok(HttpResponse::NoContent().body(""))
+                }
+                Err(err) => Err(AppError::internal_server_error(err.into())),
+            },
+            None => Err(AppError::not_found()),
+        },
+        Err(err) => Err(AppError::internal_server_error(err.into())),
+    }
+}
+
+#[utoipa::path(
+    tag = "moderation",
+    params(
+        ModerationCommentsQuery
+    ),
+    responses(
+        (status = 200, description = "Comments in the given moderation status", body = [ModerationCommentResponse]),
+        (status = 500, description = "Unexpected server error", body = HttpErrorResponse)
+    )
+)]
+#[get("/moderation/comments")]
+async fn moderation_comments_index(
+    data: web::Data<super::AppState>,
+    query: web::Query<ModerationCommentsQuery>,
+) -> Result<HttpResponse, AppError> {
+    let database_connection = &data.database_connection;
+
+    let comments_repository = repository::CommentsRepository::new(database_connection.clone());
+
+    match comments_repository
+        .find_all_by_moderation_status(query.status.unwrap_or(ModerationStatus::Pending))
+        .await
+    {
+        Ok(comments) => {
+            let response = comments
+                .into_iter()
+                .map(|comment| ModerationCommentResponse {
+                    id: comment.id,
+                    article_id: comment.article_id,
+                    parent_id: comment.parent_id,
+                    body: comment.body,
+                    moderation_status: comment.moderation_status,
+                    created_at: comment.created_at,
+                })
+                .collect::<Vec<ModerationCommentResponse>>();
+            Ok(HttpResponse::Ok().json(response))
+        }
+        Err(err) => Err(AppError::internal_server_error(err.into())),
+    }
+}
+
+#[utoipa::path(
+    tag = "moderation",
+    params(
+        ("id" = i32, Path, description = "Comment ID")
+    ),
+    responses(
+        (status = 204, description = "Comment approved"),
+        (status = 404, description = "Comment not found", body = HttpErrorResponse),
+        (status = 500, description = "Unexpected server error", body = HttpErrorResponse)
+    )
+)]
+#[post("/moderation/comments/{id}/approve")]
+async fn moderation_comments_approve(
+    data: web::Data<super::AppState>,
+    id: web::Path<i32>,
+) -> Result<HttpResponse, AppError> {
+    moderate_comment(data, id.into_inner(), ModerationStatus::Approved).await
+}
+
+#[utoipa::path(
+    tag = "moderation",
+    params(
+        ("id" = i32, Path, description = "Comment ID")
+    ),
+    responses(
+        (status = 204, description = "Comment rejected"),
+        (status = 404, description = "Comment not found", body = HttpErrorResponse),
+        (status = 500, description = "Unexpected server error", body = HttpErrorResponse)
+    )
+)]
+#[post("/moderation/comments/{id}/reject")]
+async fn moderation_comments_reject(
+    data: web::Data<super::AppState>,
+    id: web::Path<i32>,
+) -> Result<HttpResponse, AppError> {
+    moderate_comment(data, id.into_inner(), ModerationStatus::Rejected).await
+}
+
+#[utoipa::path(
+    tag = "moderation",
+    params(
+        ("id" = i32, Path, description = "Comment ID")
+    ),
+    responses(
+        (status = 204, description = "Comment marked as spam"),
+        (status = 404, description = "Comment not found", body = HttpErrorResponse),
+        (status = 500, description = "Unexpected server error", body = HttpErrorResponse)
+    )
+)]
+#[post("/moderation/comments/{id}/spam")]
+async fn moderation_comments_spam(
+    data: web::Data<super::AppState>,
+    id: web::Path<i32>,
+) -> Result<HttpResponse, AppError> {
+    moderate_comment(data, id.into_inner(), ModerationStatus::Spam).await
+}
+
+#[utoipa::path(
+    tag = "moderation",
+    responses(
+        (status = 200, description = "Moderation settings", body = BlogSettingsResponse),
+        (status = 500, description = "Unexpected server error", body = HttpErrorResponse)
+    )
+)]
+#[get("/moderation/settings")]
+async fn moderation_settings_show(
+    data: web::Data<super::AppState>,
+) -> Result<HttpResponse, AppError> {
+    let database_connection = &data.database_connection;
+
+    let blog_settings_repository =
+        repository::BlogSettingsRepository::new(database_connection.clone());
+
+    match blog_settings_repository.find().await {
+        Ok(blog_settings) => Ok(HttpResponse::Ok().json(BlogSettingsResponse {
+            comment_auto_approve: blog_settings.comment_auto_approve,
+        })),
+        Err(err) => Err(AppError::internal_server_error(err.into())),
+    }
+}
+
+#[utoipa::path(
+    tag = "moderation",
+    request_body = BlogSettingsForm,
+    responses(
+        (status = 204, description = "Moderation settings updated"),
+        (status = 500, description = "Unexpected server error", body = HttpErrorResponse)
+    )
+)]
+#[patch("/moderation/settings")]
+async fn moderation_settings_update(
+    data: web::Data<super::AppState>,
+    blog_settings_form: web::Json<BlogSettingsForm>,
+) -> Result<HttpResponse, AppError> {
+    let blog_settings_form = blog_settings_form.into_inner();
+    let database_connection = &data.database_connection;
+
+    let blog_settings_repository =
+        repository::BlogSettingsRepository::new(database_connection.clone());
+
+    let form = entity::blog_settings::Model {
+        id: 0,
+        comment_auto_approve: blog_settings_form.comment_auto_approve,
+    };
+
+    match blog_settings_repository.update(form).await {
+        Ok(_) => Ok(HttpResponse::NoContent().body("")),
+        Err(err) => Err(AppError::internal_server_error(err.into())),
+    }
+}
+
+#[utoipa::path(
+    tag = "operations",
+    responses(
+        (status = 200, description = "Metrics in the Prometheus text format", content_type = "text/plain", body = String),
+        (status = 500, description = "Unexpected server error", body = HttpErrorResponse)
+    )
+)]
+#[get("/metrics")]
+async fn metrics_show(data: web::Data<super::AppState>) -> Result<HttpResponse, AppError> {
+    match data.metrics.render(&data.database_connection) {
+        Ok(body) => Ok(HttpResponse::Ok()
+            .insert_header((header::CONTENT_TYPE, metrics::CONTENT_TYPE))
+            .body(body)),
+        Err(err) => Err(AppError::internal_server_error(err.into())),
+    }
+}
+
+#[utoipa::path(
+    tag = "operations",
+    responses(
+        (status = 200, description = "The process is up", body = HealthResponse)
+    )
+)]
+#[get("/healthz")]
+async fn health_liveness() -> Result<HttpResponse, AppError> {
+    Ok(HttpResponse::Ok().json(HealthResponse { status: "ok" }))
+}
+
+#[utoipa::path(
+    tag = "operations",
+    responses(
+        (status = 200, description = "Every dependency is available", body = ReadinessResponse),
+        (status = 503, description = "A dependency is unavailable", body = ReadinessResponse)
+    )
+)]
+#[get("/readyz")]
+async fn health_readiness(data: web::Data<super::AppState>) -> Result<HttpResponse, AppError> {
+    let database_connection = &data.database_connection;
+
+    let database = match database_connection
+        .execute(Statement::from_string(
+            database_connection.get_database_backend(),
+            "SELECT 1".to_string(),
+        ))
+        .await
+    {
+        Ok(_) => ComponentHealthResponse::ok(),
+        Err(err) => ComponentHealthResponse::error(err.to_string()),
+    };
+
+    let migrations = match migration::Migrator::get_pending_migrations(database_connection).await {
+        Ok(pending) if pending.is_empty() => ComponentHealthResponse::ok(),
+        Ok(pending) => {
+            ComponentHealthResponse::error(format!("{} pending migrations", pending.len()))
+        }
+        Err(err) => ComponentHealthResponse::error(err.to_string()),
+    };
+
+    let components = BTreeMap::from([("database", database), ("migrations", migrations)]);
+
+    if components.values().all(ComponentHealthResponse::is_ok) {
+        Ok(HttpResponse::Ok().json(ReadinessResponse {
+            status: "ok",
+            components,
+        }))
+    } else {
+        tracing::warn!("readiness check failed");
+        Ok(HttpResponse::ServiceUnavailable().json(ReadinessResponse {
+            status: "error",
+            components,
+        }))
+    }
+}
+
+#[utoipa::path(
+    tag = "webhooks",
+    responses(
+        (status = 200, description = "Registered webhooks", body = [WebhookResponse]),
+        (status = 500, description = "Unexpected server error", body = HttpErrorResponse)
+    )
+)]
+#[get("/admin/webhooks")]
+async fn admin_webhooks_index(data: web::Data<super::AppState>) -> Result<HttpResponse, AppError> {
+    let database_connection = &data.database_connection;
+
+    let webhooks_repository = repository::WebhooksRepository::new(database_connection.clone());
+
+    match webhooks_repository.find_all().await {
+        Ok(webhooks) => {
+            let response = webhooks
+                .into_iter()
+                .map(|webhook| WebhookResponse::new(webhook, false))
+                .collect::<Vec<WebhookResponse>>();
+            Ok(HttpResponse::Ok().json(response))
+        }
+        Err(err) => Err(AppError::internal_server_error(err.into())),
+    }
+}
+
+#[utoipa::path(
+    tag = "webhooks",
+    request_body = WebhookForm,
+    responses(
+        (status = 201, description = "Webhook registered, along with its secret", body = WebhookResponse),
+        (status = 400, description = "Invalid URL, event or secret", body = HttpErrorResponse),
+        (status = 500, description = "Unexpected server error", body = HttpErrorResponse)
+    )
+)]
+#[post("/admin/webhooks")]
+async fn admin_webhooks_create(
+    data: web::Data<super::AppState>,
+    webhook_form: web::Json<WebhookForm>,
+) -> Result<HttpResponse, AppError> {
+    let webhook_form = webhook_form.into_inner();
+    let database_connection = &data.database_connection;
+
+    let events = validate_webhook_form(&webhook_form)?;
+
+    let webhooks_repository = repository::WebhooksRepository::new(database_connection.clone());
+
+    let form = entity::webhooks::Model {
+        id: 0,
+        url: webhook_form.url,
+        secret: webhook_form.secret.unwrap_or_else(webhook::generate_secret),
+        events,
+        active: webhook_form.active.unwrap_or(true),
+        created_at: Utc::now(),
+        updated_at: Utc::now(),
+    };
+
+    match webhooks_repository.create(form).await {
+        Ok(webhook) => Ok(HttpResponse::Created().json(WebhookResponse::new(webhook, true))),
+        Err(err) => Err(AppError::internal_server_error(err.into())),
+    }
+}
+
+#[utoipa::path(
+    tag = "webhooks",
+    params(
+        ("id" = i32, Path, description = "Webhook ID")
+    ),
+    responses(
+        (status = 200, description = "Webhook", body = WebhookResponse),
+        (status = 404, description = "Webhook not found", body = HttpErrorResponse),
+        (status = 500, description = "Unexpected server error", body = HttpErrorResponse)
+    )
+)]
+#[get("/admin/webhooks/{id}")]
+async fn admin_webhooks_show(
+    data: web::Data<super::AppState>,
+    id: web::Path<i32>,
+) -> Result<HttpResponse, AppError> {
+    let id = id.into_inner();
+    let database_connection = &data.database_connection;
+
+    let webhooks_repository = repository::WebhooksRepository::new(database_connection.clone());
+
+    match webhooks_repository.find_by_id(id).await {
+        Ok(ok) => match ok {
+            Some(webhook) => Ok(HttpResponse::Ok().json(WebhookResponse::new(webhook, false))),
+            None => Err(AppError::not_found()),
+        },
+        Err(err) => Err(AppError::internal_server_error(err.into())),
+    }
+}
+
+#[utoipa::path(
+    tag = "webhooks",
+    params(
+        ("id" = i32, Path, description = "Webhook ID")
+    ),
+    request_body = WebhookForm,
+    responses(
+        (status = 200, description = "Webhook updated, with its secret if it was changed", body = WebhookResponse),
+        (status = 400, description = "Invalid URL, event or secret", body = HttpErrorResponse),
+        (status = 404, description = "Webhook not found", body = HttpErrorResponse),
+        (status = 500, description = "Unexpected server error", body = HttpErrorResponse)
+    )
+)]
+#[patch("/admin/webhooks/{id}")]
+async fn admin_webhooks_update(
+    data: web::Data<super::AppState>,
+    id: web::Path<i32>,
+    webhook_form: web::Json<WebhookForm>,
+) -> Result<HttpResponse, AppError> {
+    let id = id.into_inner();
+    let database_connection = &data.database_connection;
+
+    let webhooks_repository = repository::WebhooksRepository::new(database_connection.clone());
+
+    match webhooks_repository.find_by_id(id).await {
+        Ok(ok) => match ok {
+            Some(webhook) => {
+                let webhook_form = webhook_form.into_inner();
+
+                let events = validate_webhook_form(&webhook_form)?;
+                let show_secret = webhook_form.secret.is_some();
+
+                let form = entity::webhooks::Model {
+                    url: webhook_form.url,
+                    secret: webhook_form.secret.unwrap_or(webhook.secret),
+                    events,
+                    active: webhook_form.active.unwrap_or(webhook.active),
+                    ..webhook
+                };
+
+                match webhooks_repository.update(form).await {
+                    Ok(webhook) => {
+                        Ok(HttpResponse::Ok().json(WebhookResponse::new(webhook, show_secret)))
+                    }
+                    Err(err) => Err(AppError::internal_server_error(err.into())),
+                }
+            }
+            None => Err(AppError::not_found()),
+        },
+        Err(err) => Err(AppError::internal_server_error(err.into())),
+    }
+}
+
+#[utoipa::path(
+    tag = "webhooks",
+    params(
+        ("id" = i32, Path, description = "Webhook ID")
+    ),
+    responses(
+        (status = 204, description = "Webhook deleted, along with its deliveries"),
+        (status = 404, description = "Webhook not found", body = HttpErrorResponse),
+        (status = 500, description = "Unexpected server error", body = HttpErrorResponse)
+    )
+)]
+#[delete("/admin/webhooks/{id}")]
+async fn admin_webhooks_delete(
+    data: web::Data<super::AppState>,
+    id: web::Path<i32>,
+) -> Result<HttpResponse, AppError> {
+    let id = id.into_inner();
+    let database_connection = &data.database_connection;
+
+    let webhooks_repository = repository::WebhooksRepository::new(database_connection.clone());
+
+    match webhooks_repository.find_by_id(id).await {
+        Ok(ok) => match ok {
+            Some(_) => match webhooks_repository.delete(id).await {
+                Ok(_) => Ok(HttpResponse::NoContent().body("")),
+                Err(err) => Err(AppError::internal_server_error(err.into())),
+            },
+            None => Err(AppError::not_found()),
+        },
+        Err(err) => Err(AppError::internal_server_error(err.into())),
+    }
+}
+
+#[utoipa::path(
+    tag = "webhooks",
+    params(
+        ("id" = i32, Path, description = "Webhook ID")
+    ),
+    responses(
+        (status = 200, description = "The latest deliveries to the webhook, newest first", body = [WebhookDeliveryResponse]),
+        (status = 404, description = "Webhook not found", body = HttpErrorResponse),
+        (status = 500, description = "Unexpected server error", body = HttpErrorResponse)
+    )
+)]
+#[get("/admin/webhooks/{id}/deliveries")]
+async fn admin_webhook_deliveries_index(
+    data: web::Data<super::AppState>,
+    id: web::Path<i32>,
+) -> Result<HttpResponse, AppError> {
+    let id = id.into_inner();
+    let database_connection = &data.database_connection;
+
+    let webhooks_repository = repository::WebhooksRepository::new(database_connection.clone());
+
+    match webhooks_repository.find_by_id(id).await {
+        Ok(ok) => match ok {
+            Some(_) => {
+                let webhook_deliveries_repository =
+                    repository::WebhookDeliveriesRepository::new(database_connection.clone());
+
+                match webhook_deliveries_repository
+                    .find_latest_by_webhook_id(id, 100)
+                    .await
+                {
+                    Ok(deliveries) => {
+                        let response = deliveries
+                            .into_iter()
+                            .map(WebhookDeliveryResponse::from)
+                            .collect::<Vec<WebhookDeliveryResponse>>();
+                        Ok(HttpResponse::Ok().json(response))
+                    }
+                    Err(err) => Err(AppError::internal_server_error(err.into())),
+                }
+            }
+            None => Err(AppError::not_found()),
+        },
+        Err(err) => Err(AppError::internal_server_error(err.into())),
+    }
+}
+
+#[utoipa::path(
+    tag = "webhooks",
+    params(
+        ("id" = i32, Path, description = "Webhook ID"),
+        ("delivery_id" = i32, Path, description = "Delivery ID")
+    ),
+    responses(
+        (status = 200, description = "Delivery with its payload and attempt history", body = WebhookDeliveryShowResponse),
+        (status = 404, description = "Delivery not found", body = HttpErrorResponse),
+        (status = 500, description = "Unexpected server error", body = HttpErrorResponse)
+    )
+)]
+#[get("/admin/webhooks/{id}/deliveries/{delivery_id}")]
+async fn admin_webhook_deliveries_show(
+    data: web::Data<super::AppState>,
+    path_info: web::Path<(i32, i32)>,
+) -> Result<HttpResponse, AppError> {
+    let (id, delivery_id) = path_info.into_inner();
+    let database_connection = &data.database_connection;
+
+    let webhook_deliveries_repository =
+        repository::WebhookDeliveriesRepository::new(database_connection.clone());
+
+    match webhook_deliveries_repository
+        .find_by_webhook_id_and_id(id, delivery_id)
+        .await
+    {
+        Ok(ok) => match ok {
+            Some(delivery) => match webhook_deliveries_repository
+                .find_attempts(delivery.id)
+                .await
+            {
+                Ok(attempts) => {
+                    let response = WebhookDeliveryShowResponse {
+                        payload: serde_json::from_str(&delivery.payload).unwrap_or_default(),
+                        delivery: delivery.into(),
+                        attempt_history: attempts
+                            .into_iter()
+                            .map(|attempt| WebhookDeliveryAttemptResponse {
+                                attempt: attempt.attempt,
+                                response_status: attempt.response_status,
+                                error: attempt.error,
+                                duration_ms: attempt.duration_ms,
+                                created_at: attempt.created_at,
+                            })
+                            .collect(),
+                    };
+                    Ok(HttpResponse::Ok().json(response))
+                }
+                Err(err) => Err(AppError::internal_server_error(err.into())),
+            },
+            None => Err(AppError::not_found()),
+        },
+        Err(err) => Err(AppError::internal_server_error(err.into())),
+    }
+}
+
+#[utoipa::path(
+    tag = "webhooks",
+    params(
+        ("id" = i32, Path, description = "Webhook ID"),
+        ("delivery_id" = i32, Path, description = "Delivery ID")
+    ),
+    responses(
+        (status = 202, description = "A new delivery of the same payload, due immediately", body = WebhookDeliveryResponse),
+        (status = 404, description = "Delivery not found", body = HttpErrorResponse),
+        (status = 500, description = "Unexpected server error", body = HttpErrorResponse)
+    )
+)]
+#[post("/admin/webhooks/{id}/deliveries/{delivery_id}/redeliver")]
+async fn admin_webhook_deliveries_redeliver(
+    data: web::Data<super::AppState>,
+    path_info: web::Path<(i32, i32)>,
+) -> Result<HttpResponse, AppError> {
+    let (id, delivery_id) = path_info.into_inner();
+    let database_connection = &data.database_connection;
+
+    let webhook_deliveries_repository =
+        repository::WebhookDeliveriesRepository::new(database_connection.clone());
+
+    match webhook_deliveries_repository
+        .find_by_webhook_id_and_id(id, delivery_id)
+        .await
+    {
+        Ok(ok) => match ok {
+            Some(delivery) => match data.webhooks.redeliver(&delivery).await {
+                Ok(delivery) => {
+                    Ok(HttpResponse::Accepted().json(WebhookDeliveryResponse::from(delivery)))
+                }
+                Err(err) => Err(AppError::internal_server_error(err.into())),
+            },
+            None => Err(AppError::not_found()),
+        },
+        Err(err) => Err(AppError::internal_server_error(err.into())),
+    }
+}
+
+#[utoipa::path(
+    tag = "jobs",
+    params(
+        JobIndexQuery
+    ),
+    responses(
+        (status = 200, description = "The latest jobs, newest first", body = [JobResponse]),
+        (status = 500, description = "Unexpected server error", body = HttpErrorResponse)
+    )
+)]
+#[get("/admin/jobs")]
+async fn admin_jobs_index(
+    data: web::Data<super::AppState>,
+    query: web::Query<JobIndexQuery>,
+) -> Result<HttpResponse, AppError> {
+    let query = query.into_inner();
+    let database_connection = &data.database_connection;
+
+    let jobs_repository = repository::JobsRepository::new(database_connection.clone());
+
+    match jobs_repository
+        .find_latest(query.status, query.kind, 100)
+        .await
+    {
+        Ok(jobs) => {
+            let response = jobs
+                .into_iter()
+                .map(JobResponse::from)
+                .collect::<Vec<JobResponse>>();
+            Ok(HttpResponse::Ok().json(response))
+        }
+        Err(err) => Err(AppError::internal_server_error(err.into())),
+    }
+}
+
+#[utoipa::path(
+    tag = "jobs",
+    params(
+        ("id" = i32, Path, description = "Job ID")
+    ),
+    responses(
+        (status = 200, description = "Job", body = JobResponse),
+        (status = 404, description = "Job not found", body = HttpErrorResponse),
+        (status = 500, description = "Unexpected server error", body = HttpErrorResponse)
+    )
+)]
+#[get("/admin/jobs/{id}")]
+async fn admin_jobs_show(
+    data: web::Data<super::AppState>,
+    id: web::Path<i32>,
+) -> Result<HttpResponse, AppError> {
+    let id = id.into_inner();
+    let database_connection = &data.database_connection;
+
+    let jobs_repository = repository::JobsRepository::new(database_connection.clone());
+
+    match jobs_repository.find_by_id(id).await {
+        Ok(ok) => match ok {
+            Some(job) => Ok(HttpResponse::Ok().json(JobResponse::from(job))),
+            None => Err(AppError::not_found()),
+        },
+        Err(err) => Err(AppError::internal_server_error(err.into())),
+    }
+}
+
+#[utoipa::path(
+    tag = "jobs",
+    params(
+        ("id" = i32, Path, description = "Job ID")
+    ),
+    responses(
+        (status = 202, description = "Job due now, with a fresh set of attempts", body = JobResponse),
+        (status = 404, description = "Job not found", body = HttpErrorResponse),
+        (status = 409, description = "Job is running or has succeeded", body = HttpErrorResponse),
+        (status = 500, description = "Unexpected server error", body = HttpErrorResponse)
+    )
+)]
+#[post("/admin/jobs/{id}/retry")]
+async fn admin_jobs_retry(
+    data: web::Data<super::AppState>,
+    id: web::Path<i32>,
+) -> Result<HttpResponse, AppError> {
+    let id = id.into_inner();
+    let database_connection = &data.database_connection;
+
+    let jobs_repository = repository::JobsRepository::new(database_connection.clone());
+
+    match jobs_repository.find_by_id(id).await {
+        Ok(ok) => match ok {
+            Some(job) if matches!(job.status, JobStatus::Pending | JobStatus::Dead) => {
+                match jobs_repository.retry(id).await {
+                    Ok(job) => {
+                        data.jobs.wake();
+                        Ok(HttpResponse::Accepted().json(JobResponse::from(job)))
+                    }
+                    Err(err) => Err(AppError::internal_server_error(err.into())),
+                }
+            }
+            Some(_) => Err(AppError::conflict(
+                "only pending or dead jobs can be retried",
+            )),
+            None => Err(AppError::not_found()),
+        },
+        Err(err) => Err(AppError::internal_server_error(err.into())),
+    }
+}
+
+/// OpenAPI document for the JSON API, generated from the `#[utoipa::path]`
+/// annotations above. Served at `/openapi.json`, and browsable at `/docs`.
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "rust-actix-web-blog-sample"),
+    paths(
+        articles_index,
+        articles_create,
+        articles_show,
+        articles_update,
+        articles_delete,
+        article_revisions_index,
+        article_revisions_diff,
+        article_revisions_restore,
+        attachments_index,
+        attachments_create,
+        article_attachments_show,
+        attachments_delete,
+        attachments_show,
+        attachment_variants_show,
+        comments_index,
+        comments_create,
+        comments_show,
+        comments_update,
+        comments_delete,
+        comment_subscriptions_create,
+        tags_index,
+        categories_index,
+        categories_create,
+        categories_show,
+        categories_update,
+        categories_delete,
+        categories_articles_index,
+        feed_rss,
+        feed_atom,
+        comments_feed_atom,
+        sitemap_index,
+        sitemap_page,
+        moderation_comments_index,
+        moderation_comments_approve,
+        moderation_comments_reject,
+        moderation_comments_spam,
+        moderation_settings_show,
+        moderation_settings_update,
+        metrics_show,
+        health_liveness,
+        health_readiness,
+        admin_webhooks_index,
+        admin_webhooks_create,
+        admin_webhooks_show,
+        admin_webhooks_update,
+        admin_webhooks_delete,
+        admin_webhook_deliveries_index,
+        admin_webhook_deliveries_show,
+        admin_webhook_deliveries_redeliver,
+        admin_jobs_index,
+        admin_jobs_show,
+        admin_jobs_retry,
+    ),
+    components(schemas(
+        HttpErrorResponse,
+        ArticleIndexResponse,
+        ArticleShowResponse,
+        ArticleForm,
+        ArticleRevisionIndexResponse,
+        AttachmentResponse,
+        AttachmentVariantResponse,
+        AttachmentSrcsetResponse,
+        AttachmentUploadForm,
+        ArticleRevisionDiffResponse,
+        CommentIndexResponse,
+        CommentTreeResponse,
+        CommentShowResponse,
+        CommentForm,
+        CommentSubscriptionForm,
+        ModerationCommentResponse,
+        ModerationStatus,
+        BlogSettingsResponse,
+        BlogSettingsForm,
+        TagIndexResponse,
+        CategoryIndexResponse,
+        CategoryShowResponse,
+        CategoryForm,
+        HealthResponse,
+        ComponentHealthResponse,
+        ReadinessResponse,
+        WebhookResponse,
+        WebhookForm,
+        WebhookDeliveryResponse,
+        WebhookDeliveryStatus,
+        WebhookDeliveryAttemptResponse,
+        WebhookDeliveryShowResponse,
+        JobResponse,
+        JobStatus,
+    ))
+)]
+pub struct ApiDoc;
@@ -0,0 +1,159 @@
+use std::io::Cursor;
+
+use image::{
+    codecs::{jpeg::JpegEncoder, png::PngEncoder},
+    imageops::FilterType,
+    DynamicImage, ImageDecoder, ImageFormat, ImageReader, Rgb, RgbImage,
+};
+use mime::Mime;
+
+/// Widths, in pixels, of the variants made of each image, unless configured
+/// otherwise.
+pub const DEFAULT_VARIANT_WIDTHS: &str = "320,640,1024,1600";
+
+const VARIANT_JPEG_QUALITY: u8 = 82;
+const VARIANT_WEBP_QUALITY: u8 = 80;
+
+/// Quality of originals re-encoded to drop their metadata, high enough that
+/// the loss is not noticeable.
+const ORIGINAL_JPEG_QUALITY: u8 = 92;
+const ORIGINAL_WEBP_QUALITY: u8 = 92;
+
+/// An image as stored, once its metadata is gone.
+pub struct Normalized {
+    /// The re-encoded image, or `None` when the upload had no EXIF metadata
+    /// and can be kept byte for byte.
+    pub data: Option<Vec<u8>>,
+    pub width: u32,
+    pub height: u32,
+}
+
+pub struct Variant {
+    pub content_type: Mime,
+    pub data: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// The upload formats images are decoded from. GIFs are left alone, as
+/// variants would lose their animation.
+fn format(content_type: &Mime) -> Option<ImageFormat> {
+    match content_type.essence_str() {
+        "image/jpeg" => Some(ImageFormat::Jpeg),
+        "image/png" => Some(ImageFormat::Png),
+        "image/webp" => Some(ImageFormat::WebP),
+        _ => None,
+    }
+}
+
+/// Whether uploads of `content_type` get their metadata stripped and
+/// variants made.
+pub fn is_processable(content_type: &Mime) -> bool {
+    format(content_type).is_some()
+}
+
+/// Decodes `data` as `format`, turned upright according to its EXIF
+/// orientation. Also tells whether it carried EXIF metadata.
+fn decode(data: &[u8], format: ImageFormat) -> anyhow::Result<(DynamicImage, bool)> {
+    let mut decoder = ImageReader::with_format(Cursor::new(data), format).into_decoder()?;
+    let has_exif = decoder.exif_metadata()?.is_some();
+    let orientation = decoder.orientation()?;
+
+    let mut image = DynamicImage::from_decoder(decoder)?;
+    image.apply_orientation(orientation);
+
+    Ok((image, has_exif))
+}
+
+fn encode(image: &DynamicImage, format: ImageFormat, quality: u8) -> anyhow::Result<Vec<u8>> {
+    let mut data = Vec::new();
+
+    match format {
+        ImageFormat::Jpeg => {
+            JpegEncoder::new_with_quality(&mut data, quality).encode_image(&flatten(image))?
+        }
+        ImageFormat::Png => image.write_with_encoder(PngEncoder::new(&mut data))?,
+        ImageFormat::WebP => {
+            let rgba = image.to_rgba8();
+            data.extend_from_slice(
+                &webp::Encoder::from_rgba(&rgba, rgba.width(), rgba.height())
+                    .encode(quality as f32),
+            );
+        }
+        format => anyhow::bail!("cannot encode {format:?} images"),
+    }
+
+    Ok(data)
+}
+
+/// `image` without its alpha channel, over a white background, for JPEG.
+fn flatten(image: &DynamicImage) -> RgbImage {
+    let rgba = image.to_rgba8();
+
+    RgbImage::from_fn(rgba.width(), rgba.height(), |x, y| {
+        let [r, g, b, a] = rgba.get_pixel(x, y).0;
+        let blend =
+            |channel: u8| ((channel as u32 * a as u32 + 255 * (255 - a as u32) + 127) / 255) as u8;
+        Rgb([blend(r), blend(g), blend(b)])
+    })
+}
+
+/// Checks that `data` is an image of `content_type` and strips its EXIF
+/// metadata, which can give away where and with what a photo was taken. Images
+/// carrying some are re-encoded, upright, in their own format.
+pub fn normalize(data: &[u8], content_type: &Mime) -> anyhow::Result<Normalized> {
+    let format = match format(content_type) {
+        Some(format) => format,
+        None => anyhow::bail!("cannot process {} images", content_type.essence_str()),
+    };
+    let (image, has_exif) = decode(data, format)?;
+
+    let data = if has_exif {
+        let quality = match format {
+            ImageFormat::WebP => ORIGINAL_WEBP_QUALITY,
+            _ => ORIGINAL_JPEG_QUALITY,
+        };
+        Some(encode(&image, format, quality)?)
+    } else {
+        None
+    };
+
+    Ok(Normalized {
+        data,
+        width: image.width(),
+        height: image.height(),
+    })
+}
+
+/// A WebP and a JPEG of `data` at each of `widths` narrower than the image
+/// itself, which is never scaled up.
+pub fn variants(data: &[u8], content_type: &Mime, widths: &[u32]) -> anyhow::Result<Vec<Variant>> {
+    let format = match format(content_type) {
+        Some(format) => format,
+        None => anyhow::bail!("cannot process {} images", content_type.essence_str()),
+    };
+    let (image, _) = decode(data, format)?;
+
+    let mut variants = Vec::new();
+    for &width in widths.iter().filter(|&&width| width < image.width()) {
+        let height = ((image.height() as u64 * width as u64 + image.width() as u64 / 2)
+            / image.width() as u64)
+            .max(1) as u32;
+        let resized = image.resize_exact(width, height, FilterType::Lanczos3);
+
+        variants.push(Variant {
+            content_type: "image/webp".parse()?,
+            data: encode(&resized, ImageFormat::WebP, VARIANT_WEBP_QUALITY)?,
+            width,
+            height,
+        });
+        variants.push(Variant {
+            content_type: mime::IMAGE_JPEG,
+            data: encode(&resized, ImageFormat::Jpeg, VARIANT_JPEG_QUALITY)?,
+            width,
+            height,
+        });
+    }
+
+    Ok(variants)
+}
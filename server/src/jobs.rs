@@ -0,0 +1,233 @@
+use std::{env, sync::Arc, time::Duration};
+
+use chrono::Utc;
+use sea_orm::{DatabaseConnection, DbErr};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Notify;
+use tracing::Instrument;
+
+use crate::{repository, AppState};
+
+pub const DEFAULT_MAX_ATTEMPTS: i32 = 5;
+
+pub const DEFAULT_CONCURRENCY: usize = 2;
+
+/// How often workers look for due jobs when nothing wakes them up.
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How long a job may run before other workers presume its worker gone and
+/// take it over.
+const LEASE_SECONDS: i64 = 15 * 60;
+
+const BACKOFF_BASE_SECONDS: i64 = 30;
+const BACKOFF_MAX_SECONDS: i64 = 6 * 60 * 60;
+
+/// Work to be done outside the request path. Stored as JSON in the `jobs`
+/// table, so variants must stay readable by the next release.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Job {
+    DeliverWebhook { delivery_id: i32 },
+    NotifyCommentSubscribers { comment_id: i32 },
+    SendCommentEmail { comment_id: i32, email: String },
+    SendSubscriptionConfirmation { article_id: i32, email: String },
+    GenerateImageVariants { attachment_id: i32 },
+}
+
+impl Job {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::DeliverWebhook { .. } => "deliver_webhook",
+            Self::NotifyCommentSubscribers { .. } => "notify_comment_subscribers",
+            Self::SendCommentEmail { .. } => "send_comment_email",
+            Self::SendSubscriptionConfirmation { .. } => "send_subscription_confirmation",
+            Self::GenerateImageVariants { .. } => "generate_image_variants",
+        }
+    }
+
+    async fn perform(self, app_state: &AppState, run: &entity::jobs::Model) -> anyhow::Result<()> {
+        match self {
+            Self::DeliverWebhook { delivery_id } => {
+                app_state
+                    .webhooks
+                    .deliver(delivery_id, run.attempts >= run.max_attempts)
+                    .await
+            }
+            Self::NotifyCommentSubscribers { comment_id } => {
+                app_state.comment_notifier.notify(comment_id).await
+            }
+            Self::SendCommentEmail { comment_id, email } => {
+                app_state.comment_notifier.send(comment_id, &email).await
+            }
+            Self::SendSubscriptionConfirmation { article_id, email } => {
+                app_state
+                    .comment_notifier
+                    .send_subscription_confirmation(article_id, &email)
+                    .await
+            }
+            Self::GenerateImageVariants { attachment_id } => {
+                app_state.attachments.generate_variants(attachment_id).await
+            }
+        }
+    }
+}
+
+/// Delay before the run following the `attempts`th failed one: 30 seconds,
+/// doubling each time, up to 6 hours.
+pub fn backoff(attempts: i32) -> chrono::Duration {
+    let exponent = (attempts - 1).clamp(0, 20) as u32;
+
+    chrono::Duration::seconds((BACKOFF_BASE_SECONDS << exponent).min(BACKOFF_MAX_SECONDS))
+}
+
+/// Durable queue of [`Job`]s, run by workers spawned with [`JobQueue::start`].
+/// Jobs are claimed with `SELECT ... FOR UPDATE SKIP LOCKED`, so any number of
+/// workers and server instances can share the table. Failed jobs are retried
+/// with exponential backoff, then kept as `dead` for inspection.
+#[derive(Debug, Clone)]
+pub struct JobQueue {
+    database_connection: DatabaseConnection,
+    wake: Arc<Notify>,
+    max_attempts: i32,
+    concurrency: usize,
+}
+
+impl JobQueue {
+    pub fn new(
+        database_connection: DatabaseConnection,
+        max_attempts: i32,
+        concurrency: usize,
+    ) -> Self {
+        Self {
+            database_connection,
+            wake: Arc::new(Notify::new()),
+            max_attempts: max_attempts.max(1),
+            concurrency,
+        }
+    }
+
+    /// Reads the default number of attempts per job from `JOBS_MAX_ATTEMPTS`,
+    /// and the number of workers from `JOBS_CONCURRENCY`.
+    pub fn from_env(database_connection: DatabaseConnection) -> Self {
+        let max_attempts = env::var("JOBS_MAX_ATTEMPTS")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(DEFAULT_MAX_ATTEMPTS);
+        let concurrency = env::var("JOBS_CONCURRENCY")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(DEFAULT_CONCURRENCY);
+
+        Self::new(database_connection, max_attempts, concurrency)
+    }
+
+    /// Queues `job` to run as soon as a worker is free.
+    pub async fn enqueue(&self, job: Job) -> Result<entity::jobs::Model, DbErr> {
+        let jobs_repository = repository::JobsRepository::new(self.database_connection.clone());
+
+        let job = jobs_repository
+            .create(&job, self.max_attempts, Utc::now())
+            .await?;
+        self.wake();
+
+        Ok(job)
+    }
+
+    /// Queues all of `jobs`, or none of them if any cannot be.
+    pub async fn enqueue_many(&self, jobs: &[Job]) -> Result<Vec<entity::jobs::Model>, DbErr> {
+        let jobs_repository = repository::JobsRepository::new(self.database_connection.clone());
+
+        let jobs = jobs_repository
+            .create_many(jobs, self.max_attempts, Utc::now())
+            .await?;
+        self.wake();
+
+        Ok(jobs)
+    }
+
+    /// Makes a worker look for due jobs now rather than at its next poll.
+    pub fn wake(&self) {
+        self.wake.notify_one();
+    }
+
+    /// Spawns the workers. `app_state` is what jobs run with.
+    pub fn start(&self, app_state: AppState) {
+        for worker in 0..self.concurrency {
+            actix_web::rt::spawn(self.clone().work(app_state.clone(), worker));
+        }
+    }
+
+    async fn work(self, app_state: AppState, worker: usize) {
+        let jobs_repository = repository::JobsRepository::new(self.database_connection.clone());
+
+        loop {
+            let now = Utc::now();
+            match jobs_repository
+                .claim_next(now, now - chrono::Duration::seconds(LEASE_SECONDS))
+                .await
+            {
+                Ok(Some(run)) => {
+                    let span = tracing::info_span!(
+                        "job",
+                        worker,
+                        id = run.id,
+                        kind = %run.kind,
+                        attempt = run.attempts
+                    );
+                    self.run(&app_state, &jobs_repository, run)
+                        .instrument(span)
+                        .await;
+                    continue;
+                }
+                Ok(None) => {}
+                Err(err) => tracing::error!(worker, error = %err, "failed to claim a job"),
+            }
+
+            tokio::select! {
+                _ = actix_web::rt::time::sleep(POLL_INTERVAL) => {}
+                _ = self.wake.notified() => {}
+            }
+        }
+    }
+
+    async fn run(
+        &self,
+        app_state: &AppState,
+        jobs_repository: &repository::JobsRepository,
+        run: entity::jobs::Model,
+    ) {
+        let result = match serde_json::from_str::<Job>(&run.payload) {
+            Ok(job) => job.perform(app_state, &run).await,
+            Err(err) => Err(err.into()),
+        };
+
+        let recorded = match result {
+            Ok(()) => {
+                tracing::debug!("job succeeded");
+                jobs_repository.complete(run.id, run.attempts).await
+            }
+            Err(err) if run.attempts >= run.max_attempts => {
+                tracing::error!(error = %err, "job failed for the last time, moved to the dead letter queue");
+                jobs_repository
+                    .fail(run.id, run.attempts, format!("{err:#}"), None)
+                    .await
+            }
+            Err(err) => {
+                let retry_at = Utc::now() + backoff(run.attempts);
+                tracing::warn!(error = %err, %retry_at, "job failed, will be retried");
+                jobs_repository
+                    .fail(run.id, run.attempts, format!("{err:#}"), Some(retry_at))
+                    .await
+            }
+        };
+
+        match recorded {
+            Ok(true) => {}
+            // Another worker claimed the job after this run's lease expired;
+            // the outcome of its run is the one that counts.
+            Ok(false) => tracing::warn!("job lease lost, outcome not recorded"),
+            // The job is taken over again once its lease expires.
+            Err(err) => tracing::error!(error = %err, "failed to record the job outcome"),
+        }
+    }
+}
@@ -1,26 +1,61 @@
-use std::env;
+use std::{env, sync::Arc};
 
 use actix_web::{web, App, HttpServer};
 use dotenv::dotenv;
-use env_logger::Env;
 use sea_orm::{Database, DatabaseConnection};
+use utoipa::OpenApi;
+use utoipa_swagger_ui::SwaggerUi;
 
+mod attachment;
+mod cache;
+mod email;
+mod events;
+mod feed;
+mod graphql;
 mod handler;
+mod imaging;
+mod jobs;
+mod live;
+mod markdown;
+mod metrics;
 mod middleware;
+mod page;
 mod repository;
+mod sitemap;
+mod spam;
+mod storage;
+mod telemetry;
+mod webhook;
 
 #[derive(Debug, Clone)]
 pub struct AppState {
     pub database_connection: DatabaseConnection,
+    pub base_url: String,
+    pub comment_max_depth: usize,
+    pub spam_pipeline: Arc<spam::SpamPipeline>,
+    pub metrics: Arc<metrics::Metrics>,
+    pub comment_hub: live::CommentHub,
+    pub article_events: events::ArticleEventHub,
+    pub jobs: jobs::JobQueue,
+    pub webhooks: webhook::WebhookDispatcher,
+    pub comment_notifier: email::CommentNotifier,
+    pub attachments: attachment::Attachments,
+    pub article_cache: cache::ArticleCache,
 }
 
 #[actix_web::main]
 pub async fn start() -> std::io::Result<()> {
-    env_logger::init_from_env(Env::default().default_filter_or("debug"));
-
     dotenv().ok();
+    let metrics = Arc::new(metrics::Metrics::new());
+    telemetry::init(metrics.clone());
+
     let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
     let sentry_url = env::var("SENTRY_URL").expect("SENTRY_URL must be set");
+    let base_url = env::var("BASE_URL").unwrap_or_else(|_| "http://127.0.0.1:8080".to_string());
+    let comment_max_depth = env::var("COMMENT_MAX_DEPTH")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(5);
 
     let _guard = sentry::init((
         sentry_url,
@@ -33,30 +68,120 @@ pub async fn start() -> std::io::Result<()> {
     std::env::set_var("RUST_BACKTRACE", "1");
 
     let database_connection = Database::connect(&database_url).await.unwrap();
+    let spam_pipeline = spam::SpamPipeline::from_env(&database_connection)
+        .await
+        .unwrap();
+    let jobs = jobs::JobQueue::from_env(database_connection.clone());
+    let webhooks = webhook::WebhookDispatcher::from_env(database_connection.clone(), jobs.clone());
+    let base_url = base_url.trim_end_matches('/').to_string();
+    let comment_notifier = email::CommentNotifier::new(
+        database_connection.clone(),
+        jobs.clone(),
+        email::Mailer::from_env(),
+        base_url.clone(),
+    );
+    let attachments = attachment::Attachments::from_env(database_connection.clone(), jobs.clone());
+
     let app_state = AppState {
         database_connection,
+        base_url,
+        comment_max_depth,
+        spam_pipeline: Arc::new(spam_pipeline),
+        metrics: metrics.clone(),
+        comment_hub: live::CommentHub::new(),
+        article_events: events::ArticleEventHub::from_env(),
+        jobs,
+        webhooks,
+        comment_notifier,
+        attachments,
+        article_cache: cache::ArticleCache::from_env(metrics.clone()),
     };
+    app_state.jobs.start(app_state.clone());
+
+    let graphql_schema = graphql::schema(app_state.clone());
+    let rate_limiter = middleware::RateLimiter::from_env();
 
     HttpServer::new(move || {
         App::new()
-            .wrap(actix_web::middleware::Logger::default())
-            .wrap(middleware::Sentry)
+            // Registered innermost first: error events are captured inside the
+            // request's Sentry hub, and the request span carries the request ID.
             .wrap(actix_web::middleware::ErrorHandlers::new().handler(
                 actix_web::http::StatusCode::INTERNAL_SERVER_ERROR,
                 handler::notify_error_handler,
             ))
+            .wrap(rate_limiter.clone())
+            .wrap(middleware::RequestMetrics::new(metrics.clone()))
+            .wrap(middleware::Tracing)
+            .wrap(middleware::RequestId)
             .app_data(web::Data::new(app_state.clone()))
-            .service(handler::hello)
+            .app_data(web::Data::new(graphql_schema.clone()))
+            .app_data(web::Data::new(rate_limiter.clone()))
+            .service(page::articles_index)
+            .service(page::articles_show)
+            .service(page::comments_create)
+            .service(page::unsubscribe_show)
+            .service(page::unsubscribe_create)
+            .service(page::subscribe_show)
+            .service(page::subscribe_create)
             .service(handler::articles_index)
             .service(handler::articles_create)
             .service(handler::articles_show)
             .service(handler::articles_update)
             .service(handler::articles_delete)
+            .service(handler::article_revisions_index)
+            .service(handler::article_revisions_diff)
+            .service(handler::article_revisions_restore)
+            .service(handler::attachments_index)
+            .service(handler::attachments_create)
+            .service(handler::article_attachments_show)
+            .service(handler::attachments_delete)
+            .service(handler::attachments_show)
+            .service(handler::attachment_variants_show)
             .service(handler::comments_index)
             .service(handler::comments_create)
+            // Registered before `comments_show`, which would otherwise match `live` as an id.
+            .service(live::comments_live)
             .service(handler::comments_show)
             .service(handler::comments_update)
             .service(handler::comments_delete)
+            .service(handler::comment_subscriptions_create)
+            .service(handler::tags_index)
+            .service(handler::categories_index)
+            .service(handler::categories_create)
+            .service(handler::categories_show)
+            .service(handler::categories_update)
+            .service(handler::categories_delete)
+            .service(handler::categories_articles_index)
+            .service(handler::feed_rss)
+            .service(handler::feed_atom)
+            .service(handler::comments_feed_atom)
+            .service(handler::sitemap_index)
+            .service(handler::sitemap_page)
+            .service(handler::moderation_comments_index)
+            .service(handler::moderation_comments_approve)
+            .service(handler::moderation_comments_reject)
+            .service(handler::moderation_comments_spam)
+            .service(handler::moderation_settings_show)
+            .service(handler::moderation_settings_update)
+            .service(handler::metrics_show)
+            .service(handler::health_liveness)
+            .service(handler::health_readiness)
+            .service(handler::admin_webhooks_index)
+            .service(handler::admin_webhooks_create)
+            .service(handler::admin_webhooks_show)
+            .service(handler::admin_webhooks_update)
+            .service(handler::admin_webhooks_delete)
+            .service(handler::admin_webhook_deliveries_index)
+            .service(handler::admin_webhook_deliveries_show)
+            .service(handler::admin_webhook_deliveries_redeliver)
+            .service(handler::admin_jobs_index)
+            .service(handler::admin_jobs_show)
+            .service(handler::admin_jobs_retry)
+            .service(graphql::graphql)
+            .service(events::events_index)
+            .service(
+                SwaggerUi::new("/docs/{_:.*}").url("/openapi.json", handler::ApiDoc::openapi()),
+            )
     })
     .bind(("127.0.0.1", 8080))?
     .run()
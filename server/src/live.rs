@@ -0,0 +1,231 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use actix_web::{get, web, HttpRequest, HttpResponse};
+use actix_ws::{CloseCode, CloseReason, Message, MessageStream, Session};
+use chrono::{DateTime, Utc};
+use entity::sea_orm_active_enums::ModerationStatus;
+use futures_util::StreamExt;
+use serde::Serialize;
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use crate::{handler::AppError, repository};
+
+/// Events of one article a subscriber can fall behind by before it is
+/// disconnected.
+const CHANNEL_CAPACITY: usize = 256;
+
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LiveComment {
+    id: i32,
+    article_id: i32,
+    parent_id: Option<i32>,
+    body: String,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+}
+
+impl From<&entity::comments::Model> for LiveComment {
+    fn from(comment: &entity::comments::Model) -> Self {
+        Self {
+            id: comment.id,
+            article_id: comment.article_id,
+            parent_id: comment.parent_id,
+            body: comment.body.clone(),
+            created_at: comment.created_at,
+            updated_at: comment.updated_at,
+        }
+    }
+}
+
+/// A change to the comments readers can see. A comment that gets approved is
+/// `created`; one that is deleted or leaves the approved state is `deleted`,
+/// and so are its replies.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CommentEvent {
+    Created { comment: LiveComment },
+    Updated { comment: LiveComment },
+    Deleted { id: i32, article_id: i32 },
+}
+
+impl CommentEvent {
+    fn article_id(&self) -> i32 {
+        match self {
+            Self::Created { comment } | Self::Updated { comment } => comment.article_id,
+            Self::Deleted { article_id, .. } => *article_id,
+        }
+    }
+}
+
+/// In-process broadcast of comment events to every open live connection. Each
+/// article has its own channel, so that a busy thread does not make readers of
+/// a quiet one fall behind. A channel is dropped once nobody listens to it.
+#[derive(Debug, Clone, Default)]
+pub struct CommentHub {
+    senders: Arc<Mutex<HashMap<i32, broadcast::Sender<CommentEvent>>>>,
+}
+
+impl CommentHub {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn subscribe(&self, article_id: i32) -> broadcast::Receiver<CommentEvent> {
+        let mut senders = self.senders.lock().unwrap();
+
+        senders
+            .entry(article_id)
+            .or_insert_with(|| broadcast::channel(CHANNEL_CAPACITY).0)
+            .subscribe()
+    }
+
+    /// Drops the channel of `article_id` if its last receiver is gone.
+    fn unsubscribed(&self, article_id: i32) {
+        let mut senders = self.senders.lock().unwrap();
+
+        if let Some(sender) = senders.get(&article_id) {
+            if sender.receiver_count() == 0 {
+                senders.remove(&article_id);
+            }
+        }
+    }
+
+    fn publish(&self, event: CommentEvent) {
+        let mut senders = self.senders.lock().unwrap();
+        let article_id = event.article_id();
+
+        if let Some(sender) = senders.get(&article_id) {
+            // Sending only fails when nobody is listening.
+            if sender.send(event).is_err() {
+                senders.remove(&article_id);
+            }
+        }
+    }
+
+    /// Publishes a comment that was just created or changed. `previous` is its
+    /// moderation status before the change, `None` for a new comment.
+    pub fn saved(&self, comment: &entity::comments::Model, previous: Option<ModerationStatus>) {
+        let was_visible = previous == Some(ModerationStatus::Approved);
+        let is_visible = comment.moderation_status == ModerationStatus::Approved;
+
+        match (was_visible, is_visible) {
+            (false, true) => self.publish(CommentEvent::Created {
+                comment: comment.into(),
+            }),
+            (true, true) => self.publish(CommentEvent::Updated {
+                comment: comment.into(),
+            }),
+            (true, false) => self.publish(CommentEvent::Deleted {
+                id: comment.id,
+                article_id: comment.article_id,
+            }),
+            (false, false) => {}
+        }
+    }
+
+    /// Publishes the deletion of `comment`, as it was before being deleted.
+    pub fn deleted(&self, comment: &entity::comments::Model) {
+        if comment.moderation_status == ModerationStatus::Approved {
+            self.publish(CommentEvent::Deleted {
+                id: comment.id,
+                article_id: comment.article_id,
+            });
+        }
+    }
+}
+
+#[get("/articles/{article_id}/comments/live")]
+async fn comments_live(
+    request: HttpRequest,
+    payload: web::Payload,
+    data: web::Data<super::AppState>,
+    path_info: web::Path<i32>,
+) -> Result<HttpResponse, AppError> {
+    let article_id = path_info.into_inner();
+    let database_connection = &data.database_connection;
+
+    let articles_repository = repository::ArticlesRepository::new(database_connection.clone());
+
+    match articles_repository.find_by_id(article_id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return Err(AppError::not_found()),
+        Err(err) => return Err(AppError::internal_server_error(err.into())),
+    }
+
+    let (response, session, messages) = match actix_ws::handle(&request, payload) {
+        Ok(handshake) => handshake,
+        Err(err) => return Err(AppError::bad_request(&err.to_string())),
+    };
+
+    // Subscribe before answering, so no event published after the handshake is missed.
+    let hub = data.comment_hub.clone();
+    let events = hub.subscribe(article_id);
+    actix_web::rt::spawn(async move {
+        forward_comment_events(events, session, messages).await;
+        hub.unsubscribed(article_id);
+    });
+
+    Ok(response)
+}
+
+/// Sends the article's comment events to the client as JSON text messages
+/// until either side goes away.
+async fn forward_comment_events(
+    mut events: broadcast::Receiver<CommentEvent>,
+    mut session: Session,
+    mut messages: MessageStream,
+) {
+    let mut heartbeat = actix_web::rt::time::interval(HEARTBEAT_INTERVAL);
+
+    let reason = loop {
+        tokio::select! {
+            event = events.recv() => match event {
+                Ok(event) => {
+                    let text = match serde_json::to_string(&event) {
+                        Ok(text) => text,
+                        Err(err) => {
+                            tracing::error!(error = %err, "failed to serialize comment event");
+                            continue;
+                        }
+                    };
+
+                    if session.text(text).await.is_err() {
+                        break None;
+                    }
+                }
+                // The client missed events; make it reconnect and reload the thread.
+                Err(RecvError::Lagged(_)) => {
+                    break Some(CloseReason {
+                        code: CloseCode::Again,
+                        description: Some("missed comment events, reconnect".to_string()),
+                    });
+                }
+                Err(RecvError::Closed) => break Some(CloseCode::Away.into()),
+            },
+            message = messages.next() => match message {
+                Some(Ok(Message::Ping(bytes))) => {
+                    if session.pong(&bytes).await.is_err() {
+                        break None;
+                    }
+                }
+                Some(Ok(Message::Close(reason))) => break reason,
+                // Nothing else is expected from clients.
+                Some(Ok(_)) => {}
+                Some(Err(_)) | None => break None,
+            },
+            _ = heartbeat.tick() => {
+                if session.ping(b"").await.is_err() {
+                    break None;
+                }
+            }
+        }
+    };
+
+    let _ = session.close(reason).await;
+}
@@ -0,0 +1,24 @@
+use comrak::{markdown_to_html, ComrakOptions};
+
+/// Renders an article body written in CommonMark with the GitHub Flavored
+/// Markdown extensions, and sanitizes the result so it can be embedded in a
+/// page as is.
+pub fn render_html(markdown: &str) -> String {
+    let mut options = ComrakOptions::default();
+    options.extension.strikethrough = true;
+    options.extension.tagfilter = true;
+    options.extension.table = true;
+    options.extension.autolink = true;
+    options.extension.tasklist = true;
+    options.extension.footnotes = true;
+
+    let html = markdown_to_html(markdown, &options);
+
+    ammonia::Builder::default()
+        .add_tags(&["input"])
+        .add_tag_attribute_values("input", "type", &["checkbox"])
+        .add_tag_attributes("input", &["checked", "disabled"])
+        .add_tag_attributes("code", &["class"])
+        .clean(&html)
+        .to_string()
+}
@@ -0,0 +1,238 @@
+use std::{
+    fmt,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use entity::sea_orm_active_enums::ModerationStatus;
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGaugeVec, Opts, Registry,
+    TextEncoder,
+};
+use sea_orm::DatabaseConnection;
+use tracing::{span, Subscriber};
+use tracing_subscriber::{layer::Context, registry::LookupSpan, Layer};
+
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Target of the spans opened by `#[tracing::instrument]` on repository methods.
+pub const REPOSITORY_TARGET: &str = "server::repository";
+
+/// Everything exposed on `GET /metrics`. Shared through `AppState`, and with
+/// the tracing subscriber for repository query durations.
+pub struct Metrics {
+    registry: Registry,
+    http_requests_total: IntCounterVec,
+    http_request_duration_seconds: HistogramVec,
+    db_pool_connections: IntGaugeVec,
+    repository_query_duration_seconds: HistogramVec,
+    articles_created_total: IntCounter,
+    comments_created_total: IntCounterVec,
+    article_cache_hits_total: IntCounter,
+    article_cache_misses_total: IntCounter,
+}
+
+impl fmt::Debug for Metrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Metrics").finish_non_exhaustive()
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        let registry = Registry::new();
+
+        let http_requests_total = IntCounterVec::new(
+            Opts::new("http_requests_total", "HTTP requests handled."),
+            &["method", "route", "status"],
+        )
+        .unwrap();
+        let http_request_duration_seconds = HistogramVec::new(
+            HistogramOpts::new(
+                "http_request_duration_seconds",
+                "Time spent handling HTTP requests.",
+            ),
+            &["method", "route", "status"],
+        )
+        .unwrap();
+        let db_pool_connections = IntGaugeVec::new(
+            Opts::new("db_pool_connections", "Open database connections by state."),
+            &["state"],
+        )
+        .unwrap();
+        let repository_query_duration_seconds = HistogramVec::new(
+            HistogramOpts::new(
+                "repository_query_duration_seconds",
+                "Time spent in repository methods.",
+            )
+            .buckets(vec![
+                0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5,
+            ]),
+            &["method"],
+        )
+        .unwrap();
+        let articles_created_total =
+            IntCounter::new("articles_created_total", "Articles created.").unwrap();
+        let comments_created_total = IntCounterVec::new(
+            Opts::new(
+                "comments_created_total",
+                "Comments created, by the moderation status they were stored with.",
+            ),
+            &["moderation_status"],
+        )
+        .unwrap();
+        let article_cache_hits_total = IntCounter::new(
+            "article_cache_hits_total",
+            "Article reads answered from the in-process cache.",
+        )
+        .unwrap();
+        let article_cache_misses_total = IntCounter::new(
+            "article_cache_misses_total",
+            "Article reads that missed the in-process cache and went to the database.",
+        )
+        .unwrap();
+
+        registry
+            .register(Box::new(http_requests_total.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(http_request_duration_seconds.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(db_pool_connections.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(repository_query_duration_seconds.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(articles_created_total.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(comments_created_total.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(article_cache_hits_total.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(article_cache_misses_total.clone()))
+            .unwrap();
+
+        Self {
+            registry,
+            http_requests_total,
+            http_request_duration_seconds,
+            db_pool_connections,
+            repository_query_duration_seconds,
+            articles_created_total,
+            comments_created_total,
+            article_cache_hits_total,
+            article_cache_misses_total,
+        }
+    }
+
+    /// `route` is the matched route pattern rather than the path, so that the
+    /// number of series stays bounded.
+    pub fn observe_request(&self, method: &str, route: &str, status: u16, elapsed: Duration) {
+        let status = status.to_string();
+        let labels = [method, route, status.as_str()];
+
+        self.http_requests_total.with_label_values(&labels).inc();
+        self.http_request_duration_seconds
+            .with_label_values(&labels)
+            .observe(elapsed.as_secs_f64());
+    }
+
+    pub fn observe_query(&self, method: &str, elapsed: Duration) {
+        self.repository_query_duration_seconds
+            .with_label_values(&[method])
+            .observe(elapsed.as_secs_f64());
+    }
+
+    pub fn article_created(&self) {
+        self.articles_created_total.inc();
+    }
+
+    pub fn comment_created(&self, moderation_status: ModerationStatus) {
+        let moderation_status = match moderation_status {
+            ModerationStatus::Pending => "pending",
+            ModerationStatus::Approved => "approved",
+            ModerationStatus::Rejected => "rejected",
+            ModerationStatus::Spam => "spam",
+        };
+
+        self.comments_created_total
+            .with_label_values(&[moderation_status])
+            .inc();
+    }
+
+    pub fn article_cache_hit(&self) {
+        self.article_cache_hits_total.inc();
+    }
+
+    pub fn article_cache_miss(&self) {
+        self.article_cache_misses_total.inc();
+    }
+
+    /// Samples the connection pool and encodes every metric in the Prometheus
+    /// text format.
+    pub fn render(
+        &self,
+        database_connection: &DatabaseConnection,
+    ) -> Result<String, prometheus::Error> {
+        let pool = database_connection.get_mysql_connection_pool();
+        let open = pool.size() as i64;
+        let idle = pool.num_idle() as i64;
+
+        self.db_pool_connections
+            .with_label_values(&["idle"])
+            .set(idle);
+        self.db_pool_connections
+            .with_label_values(&["in_use"])
+            .set(open - idle);
+
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer)?;
+
+        String::from_utf8(buffer).map_err(|err| prometheus::Error::Msg(err.to_string()))
+    }
+}
+
+/// Records how long each repository span stays open. Meant to be installed
+/// with a filter on [`REPOSITORY_TARGET`], so that other spans are ignored.
+pub struct QueryDurationLayer {
+    metrics: Arc<Metrics>,
+}
+
+struct SpanStartedAt(Instant);
+
+impl QueryDurationLayer {
+    pub fn new(metrics: Arc<Metrics>) -> Self {
+        Self { metrics }
+    }
+}
+
+impl<S> Layer<S> for QueryDurationLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, _attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(SpanStartedAt(Instant::now()));
+        }
+    }
+
+    fn on_close(&self, id: span::Id, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(&id) {
+            if let Some(started_at) = span.extensions().get::<SpanStartedAt>() {
+                self.metrics
+                    .observe_query(span.name(), started_at.0.elapsed());
+            }
+        }
+    }
+}
@@ -1,13 +1,62 @@
 use actix_web::{
+    body::EitherBody,
     dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
-    Error,
+    http::{
+        header::{HeaderMap, HeaderName, HeaderValue, RETRY_AFTER, X_FORWARDED_FOR},
+        Method,
+    },
+    Error, HttpMessage, HttpRequest, HttpResponse,
 };
 use futures_util::future::LocalBoxFuture;
-use std::future::{ready, Ready};
+use lru::LruCache;
+use sentry::{Hub, SentryFutureExt};
+use std::{
+    env,
+    future::{ready, Ready},
+    num::NonZeroUsize,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tracing::Instrument;
+
+use crate::{handler::HttpErrorResponse, metrics::Metrics};
+
+/// Header carrying the request ID, both on the way in and on the way out.
+pub const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+const REQUEST_ID_MAX_LEN: usize = 128;
 
-pub struct Sentry;
+/// The ID assigned to the current request. Stored in the request extensions by
+/// [`RequestId`] middleware.
+#[derive(Debug, Clone)]
+pub struct RequestIdValue(pub String);
+
+impl RequestIdValue {
+    /// Accepts a client supplied ID as long as it is short and made of
+    /// characters that are safe to echo into headers and log lines.
+    fn from_header(value: &HeaderValue) -> Option<Self> {
+        let value = value.to_str().ok()?;
+        let valid = !value.is_empty()
+            && value.len() <= REQUEST_ID_MAX_LEN
+            && value
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':'));
+
+        valid.then(|| Self(value.to_string()))
+    }
 
-impl<S, B> Transform<S, ServiceRequest> for Sentry
+    fn generate() -> Self {
+        Self(sentry::types::Uuid::new_v4().to_string())
+    }
+}
+
+/// Accepts the `X-Request-Id` sent by the client, or generates one, and makes
+/// it visible everywhere a request leaves a trace: the request headers seen by
+/// the access log, the request extensions, the response headers and the tags
+/// of a Sentry hub bound to the request.
+pub struct RequestId;
+
+impl<S, B> Transform<S, ServiceRequest> for RequestId
 where
     S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
     S::Future: 'static,
@@ -16,19 +65,87 @@ where
     type Response = ServiceResponse<B>;
     type Error = Error;
     type InitError = ();
-    type Transform = SentryMiddleware<S>;
+    type Transform = RequestIdMiddleware<S>;
     type Future = Ready<Result<Self::Transform, Self::InitError>>;
 
     fn new_transform(&self, service: S) -> Self::Future {
-        ready(Ok(SentryMiddleware { service }))
+        ready(Ok(RequestIdMiddleware { service }))
     }
 }
 
-pub struct SentryMiddleware<S> {
+pub struct RequestIdMiddleware<S> {
     service: S,
 }
 
-impl<S, B> Service<ServiceRequest> for SentryMiddleware<S>
+impl<S, B> Service<ServiceRequest> for RequestIdMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, mut req: ServiceRequest) -> Self::Future {
+        let request_id = req
+            .headers()
+            .get(&REQUEST_ID_HEADER)
+            .and_then(RequestIdValue::from_header)
+            .unwrap_or_else(RequestIdValue::generate);
+        // Validated or generated above, so it is always a valid header value.
+        let header_value = HeaderValue::from_str(&request_id.0).unwrap();
+
+        req.headers_mut()
+            .insert(REQUEST_ID_HEADER, header_value.clone());
+        req.extensions_mut().insert(request_id.clone());
+
+        let hub = Arc::new(Hub::new_from_top(Hub::main()));
+        hub.configure_scope(|scope| scope.set_tag("request_id", &request_id.0));
+
+        let fut = Hub::run(hub.clone(), || self.service.call(req));
+
+        Box::pin(
+            async move {
+                let mut res = fut.await?;
+                res.headers_mut().insert(REQUEST_ID_HEADER, header_value);
+                Ok(res)
+            }
+            .bind_hub(hub),
+        )
+    }
+}
+
+/// Wraps each request in an `http_request` span carrying the method, route
+/// pattern and request ID, and records the status and latency once the response
+/// is ready. Anything logged while handling the request, repository calls
+/// included, nests under this span. Must be registered inside [`RequestId`].
+pub struct Tracing;
+
+impl<S, B> Transform<S, ServiceRequest> for Tracing
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = TracingMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(TracingMiddleware { service }))
+    }
+}
+
+pub struct TracingMiddleware<S> {
+    service: S,
+}
+
+impl<S, B> Service<ServiceRequest> for TracingMiddleware<S>
 where
     S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
     S::Future: 'static,
@@ -41,26 +158,486 @@ where
     forward_ready!(service);
 
     fn call(&self, req: ServiceRequest) -> Self::Future {
-        println!("Hi from start. You requested: {}", req.path());
+        let route = req
+            .match_pattern()
+            .unwrap_or_else(|| "unmatched".to_string());
+        let request_id = req
+            .extensions()
+            .get::<RequestIdValue>()
+            .map(|request_id| request_id.0.clone())
+            .unwrap_or_default();
+        let span = tracing::info_span!(
+            "http_request",
+            method = %req.method(),
+            route = %route,
+            request_id = %request_id,
+            status = tracing::field::Empty,
+            latency_ms = tracing::field::Empty,
+        );
+
+        let started_at = Instant::now();
+        let fut = span.in_scope(|| self.service.call(req));
 
+        Box::pin(
+            async move {
+                let result = fut.await;
+                let span = tracing::Span::current();
+                span.record("latency_ms", started_at.elapsed().as_secs_f64() * 1000.0);
+
+                match &result {
+                    Ok(res) => {
+                        span.record("status", res.status().as_u16());
+                        tracing::info!("request completed");
+                    }
+                    Err(err) => {
+                        let status = err.as_response_error().status_code();
+                        span.record("status", status.as_u16());
+                        tracing::warn!(error = %err, "request failed");
+                    }
+                }
+
+                result
+            }
+            .instrument(span),
+        )
+    }
+}
+
+/// Counts requests and observes their latency by method, route pattern and
+/// status for `GET /metrics`.
+pub struct RequestMetrics {
+    metrics: Arc<Metrics>,
+}
+
+impl RequestMetrics {
+    pub fn new(metrics: Arc<Metrics>) -> Self {
+        Self { metrics }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RequestMetrics
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = RequestMetricsMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequestMetricsMiddleware {
+            service,
+            metrics: self.metrics.clone(),
+        }))
+    }
+}
+
+pub struct RequestMetricsMiddleware<S> {
+    service: S,
+    metrics: Arc<Metrics>,
+}
+
+impl<S, B> Service<ServiceRequest> for RequestMetricsMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let method = req.method().to_string();
+        let route = req
+            .match_pattern()
+            .unwrap_or_else(|| "unmatched".to_string());
+        let metrics = self.metrics.clone();
+
+        let started_at = Instant::now();
         let fut = self.service.call(req);
 
         Box::pin(async move {
-            match fut.await {
-                Ok(res) => {
-                    println!("Hi from response");
-                    return Ok(res);
-                }
-                Err(err) => {
-                    println!("Hi from error");
-                    return Err(err);
-                }
+            let result = fut.await;
+            let status = match &result {
+                Ok(res) => res.status(),
+                Err(err) => err.as_response_error().status_code(),
+            };
+            metrics.observe_request(&method, &route, status.as_u16(), started_at.elapsed());
+
+            result
+        })
+    }
+}
+
+/// Allows `capacity` requests in a burst, refilled evenly over `period`.
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimit {
+    pub capacity: u32,
+    pub period: Duration,
+}
+
+impl RateLimit {
+    /// Parses `<capacity>/<seconds>`, e.g. `5/60`.
+    fn parse(value: &str) -> Option<Self> {
+        let (capacity, seconds) = value.split_once('/')?;
+        let capacity = capacity.trim().parse().ok()?;
+        let seconds: u64 = seconds.trim().parse().ok()?;
+
+        if capacity == 0 || seconds == 0 {
+            return None;
+        }
+
+        Some(Self {
+            capacity,
+            period: Duration::from_secs(seconds),
+        })
+    }
+
+    fn refill_per_second(&self) -> f64 {
+        self.capacity as f64 / self.period.as_secs_f64()
+    }
+}
+
+/// A set of routes sharing one limit. Requests are matched against the route
+/// pattern they resolve to, e.g. `/articles/{article_id}/comments`.
+pub struct RateLimitGroup {
+    pub name: &'static str,
+    pub limit: RateLimit,
+    pub matches: fn(&Method, &str) -> bool,
+}
+
+impl RateLimitGroup {
+    /// Builds a group whose limit can be overridden by the `env_key` environment
+    /// variable. Setting it to `off` disables the group.
+    fn from_env(
+        name: &'static str,
+        env_key: &str,
+        default: RateLimit,
+        matches: fn(&Method, &str) -> bool,
+    ) -> Option<Self> {
+        let limit = match env::var(env_key) {
+            Ok(value) if value.trim() == "off" => return None,
+            Ok(value) => RateLimit::parse(&value).unwrap_or(default),
+            Err(_) => default,
+        };
+
+        Some(Self {
+            name,
+            limit,
+            matches,
+        })
+    }
+}
+
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+impl Bucket {
+    fn refill(&mut self, limit: &RateLimit, now: Instant) {
+        let elapsed = now.duration_since(self.updated_at).as_secs_f64();
+        self.tokens =
+            (self.tokens + elapsed * limit.refill_per_second()).min(limit.capacity as f64);
+        self.updated_at = now;
+    }
+}
+
+struct RateLimitDecision {
+    allowed: bool,
+    limit: RateLimit,
+    remaining: u32,
+    retry_after: u64,
+    reset: u64,
+}
+
+impl RateLimitDecision {
+    fn apply_headers(&self, headers: &mut HeaderMap) {
+        let values = [
+            ("ratelimit-limit", self.limit.capacity as u64),
+            ("ratelimit-remaining", self.remaining as u64),
+            ("ratelimit-reset", self.reset),
+        ];
+        for (name, value) in values {
+            headers.insert(HeaderName::from_static(name), HeaderValue::from(value));
+        }
+        if !self.allowed {
+            headers.insert(RETRY_AFTER, HeaderValue::from(self.retry_after));
+        }
+    }
+}
+
+/// Most buckets tracked at once. Past it, the least recently used bucket is
+/// dropped to make room, which at worst hands a quiet client a fresh bucket.
+const MAX_BUCKETS: usize = 100_000;
+
+/// How often buckets that have refilled completely are swept away, so that
+/// clients which went away do not take up room until they are evicted.
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+struct Buckets {
+    entries: LruCache<(&'static str, String), Bucket>,
+    swept_at: Instant,
+}
+
+struct RateLimiterState {
+    groups: Vec<RateLimitGroup>,
+    proxy_hops: usize,
+    buckets: Mutex<Buckets>,
+}
+
+/// Token bucket rate limiting per client and route group. Clients are identified
+/// by their IP address. Behind `proxy_hops` trusted proxies, it is taken from
+/// `X-Forwarded-For`, counting that many entries from the right: those are the
+/// ones the proxies appended, while anything further left came from the client.
+#[derive(Clone)]
+pub struct RateLimiter {
+    state: Arc<RateLimiterState>,
+}
+
+impl RateLimiter {
+    pub fn new(groups: Vec<RateLimitGroup>, proxy_hops: usize) -> Self {
+        Self {
+            state: Arc::new(RateLimiterState {
+                groups,
+                proxy_hops,
+                buckets: Mutex::new(Buckets {
+                    entries: LruCache::new(NonZeroUsize::new(MAX_BUCKETS).unwrap()),
+                    swept_at: Instant::now(),
+                }),
+            }),
+        }
+    }
+
+    /// Limits comment submissions, other writes and reads separately. Each limit
+    /// is read from `RATE_LIMIT_COMMENTS`, `RATE_LIMIT_WRITES` and
+    /// `RATE_LIMIT_READS` as `<capacity>/<seconds>`. Health checks and metrics
+    /// scrapes are never limited. `RATE_LIMIT_TRUST_PROXY` trusts
+    /// `X-Forwarded-For` from `RATE_LIMIT_PROXY_HOPS` proxies, one by default.
+    pub fn from_env() -> Self {
+        let groups = [
+            RateLimitGroup::from_env(
+                "comments",
+                "RATE_LIMIT_COMMENTS",
+                RateLimit {
+                    capacity: 5,
+                    period: Duration::from_secs(60),
+                },
+                |method, pattern| {
+                    method == Method::POST
+                        && matches!(
+                            pattern,
+                            "/articles/{article_id}/comments" | "/posts/{id}/comments"
+                        )
+                },
+            ),
+            RateLimitGroup::from_env(
+                "writes",
+                "RATE_LIMIT_WRITES",
+                RateLimit {
+                    capacity: 30,
+                    period: Duration::from_secs(60),
+                },
+                |method, _| !matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS),
+            ),
+            RateLimitGroup::from_env(
+                "reads",
+                "RATE_LIMIT_READS",
+                RateLimit {
+                    capacity: 120,
+                    period: Duration::from_secs(60),
+                },
+                |method, pattern| {
+                    matches!(*method, Method::GET | Method::HEAD)
+                        && !matches!(pattern, "/healthz" | "/readyz" | "/metrics")
+                },
+            ),
+        ];
+
+        let trust_proxy = env::var("RATE_LIMIT_TRUST_PROXY")
+            .map(|value| value == "true")
+            .unwrap_or(false);
+        let proxy_hops = if trust_proxy {
+            env::var("RATE_LIMIT_PROXY_HOPS")
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(1)
+        } else {
+            0
+        };
+
+        Self::new(groups.into_iter().flatten().collect(), proxy_hops)
+    }
+
+    /// Identifies the client `req` came from, as buckets are keyed.
+    pub fn client_key(&self, req: &HttpRequest) -> String {
+        if self.state.proxy_hops > 0 {
+            if let Some(addr) = self.forwarded_for(req) {
+                return addr;
             }
+        }
+
+        req.peer_addr()
+            .map(|addr| addr.ip().to_string())
+            .unwrap_or_else(|| "unknown".to_string())
+    }
+
+    /// The address the outermost trusted proxy saw the request come from, or
+    /// `None` when it did not go through as many proxies as configured.
+    fn forwarded_for(&self, req: &HttpRequest) -> Option<String> {
+        let addrs = req
+            .headers()
+            .get_all(X_FORWARDED_FOR)
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .map(str::trim)
+            .collect::<Vec<_>>();
+        let index = addrs.len().checked_sub(self.state.proxy_hops)?;
+
+        Some(addrs[index])
+            .filter(|addr| !addr.is_empty())
+            .map(str::to_string)
+    }
+
+    /// Drops the buckets that have refilled completely, as a new bucket would
+    /// behave the same.
+    fn sweep(&self, entries: &mut LruCache<(&'static str, String), Bucket>, now: Instant) {
+        let idle = entries
+            .iter_mut()
+            .filter_map(|(key, bucket)| {
+                let group = self.state.groups.iter().find(|group| group.name == key.0);
+                match group {
+                    Some(group) => {
+                        bucket.refill(&group.limit, now);
+                        (bucket.tokens >= group.limit.capacity as f64).then(|| key.clone())
+                    }
+                    None => Some(key.clone()),
+                }
+            })
+            .collect::<Vec<_>>();
+        for key in idle {
+            entries.pop(&key);
+        }
+    }
+
+    /// Takes a token from `client`'s bucket in the group called `name`, for
+    /// limits that apply to part of a request, such as each comment created
+    /// through GraphQL. Always allowed when that group is disabled.
+    pub fn acquire(&self, name: &str, client: String) -> bool {
+        match self.state.groups.iter().find(|group| group.name == name) {
+            Some(group) => self.take(group, client).allowed,
+            None => true,
+        }
+    }
+
+    fn check(&self, req: &ServiceRequest) -> Option<RateLimitDecision> {
+        let pattern = req.match_pattern()?;
+        let group = self
+            .state
+            .groups
+            .iter()
+            .find(|group| (group.matches)(req.method(), &pattern))?;
+
+        Some(self.take(group, self.client_key(req.request())))
+    }
+
+    fn take(&self, group: &RateLimitGroup, client: String) -> RateLimitDecision {
+        let limit = group.limit;
+        let now = Instant::now();
+
+        let mut buckets = self.state.buckets.lock().unwrap();
+
+        if now.duration_since(buckets.swept_at) >= SWEEP_INTERVAL {
+            self.sweep(&mut buckets.entries, now);
+            buckets.swept_at = now;
+        }
+
+        let bucket = buckets
+            .entries
+            .get_or_insert_mut((group.name, client), || Bucket {
+                tokens: limit.capacity as f64,
+                updated_at: now,
+            });
+        bucket.refill(&limit, now);
+
+        let allowed = bucket.tokens >= 1.0;
+        if allowed {
+            bucket.tokens -= 1.0;
+        }
+
+        let rate = limit.refill_per_second();
+        RateLimitDecision {
+            allowed,
+            limit,
+            remaining: bucket.tokens.floor() as u32,
+            retry_after: ((1.0 - bucket.tokens).max(0.0) / rate).ceil().max(1.0) as u64,
+            reset: ((limit.capacity as f64 - bucket.tokens) / rate).ceil() as u64,
+        }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RateLimiter
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = RateLimiterMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
 
-            // let res = fut.await?;
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RateLimiterMiddleware {
+            service,
+            rate_limiter: self.clone(),
+        }))
+    }
+}
+
+pub struct RateLimiterMiddleware<S> {
+    service: S,
+    rate_limiter: RateLimiter,
+}
+
+impl<S, B> Service<ServiceRequest> for RateLimiterMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let decision = self.rate_limiter.check(&req);
 
-            // println!("Hi from response");
-            // Ok(res)
+        if let Some(decision) = decision.as_ref().filter(|decision| !decision.allowed) {
+            let mut response =
+                HttpResponse::TooManyRequests().json(HttpErrorResponse::too_many_requests());
+            decision.apply_headers(response.headers_mut());
+
+            return Box::pin(ready(Ok(req.into_response(response).map_into_right_body())));
+        }
+
+        let fut = self.service.call(req);
+
+        Box::pin(async move {
+            let mut res = fut.await?;
+            if let Some(decision) = decision {
+                decision.apply_headers(res.headers_mut());
+            }
+            Ok(res.map_into_left_body())
         })
     }
 }
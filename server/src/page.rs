@@ -0,0 +1,415 @@
+use actix_web::{
+    get,
+    http::{header, header::ContentType, StatusCode},
+    post, web, HttpResponse,
+};
+use askama::Template;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+
+use crate::{handler::AppError, markdown, repository, spam, webhook};
+use entity::sea_orm_active_enums::ModerationStatus;
+use sea_orm::TryIntoModel;
+
+pub fn article_path(id: i32) -> String {
+    format!("/posts/{id}")
+}
+
+pub fn comments_path(article_id: i32) -> String {
+    format!("/posts/{article_id}/comments")
+}
+
+/// Where unsubscribe links in notification emails point, with a `token` query.
+pub const UNSUBSCRIBE_PATH: &str = "/unsubscribe";
+
+pub const SUBSCRIBE_PATH: &str = "/subscribe";
+
+struct ArticleSummary {
+    path: String,
+    title: String,
+    created_at: DateTime<Utc>,
+    tags: Vec<String>,
+}
+
+#[derive(Template)]
+#[template(path = "index.html")]
+struct IndexTemplate {
+    articles: Vec<ArticleSummary>,
+}
+
+#[derive(Template)]
+#[template(path = "article.html")]
+struct ArticleTemplate {
+    article: entity::articles::Model,
+    body_html: String,
+    tags: Vec<String>,
+    comments: Vec<entity::comments::Model>,
+    comments_path: String,
+    comment_body: String,
+    error: Option<String>,
+    notice: Option<String>,
+}
+
+#[derive(Template)]
+#[template(path = "not_found.html")]
+struct NotFoundTemplate;
+
+#[derive(Template)]
+#[template(path = "unsubscribe.html")]
+struct UnsubscribeTemplate {
+    email: String,
+    article_title: String,
+    article_path: String,
+    action: String,
+    unsubscribed: bool,
+}
+
+#[derive(Template)]
+#[template(path = "subscribe.html")]
+struct SubscribeTemplate {
+    email: String,
+    article_title: String,
+    article_path: String,
+    action: String,
+    subscribed: bool,
+}
+
+#[derive(Deserialize)]
+struct ArticleQuery {
+    comment: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct CommentForm {
+    body: String,
+}
+
+#[derive(Deserialize)]
+struct UnsubscribeQuery {
+    token: String,
+}
+
+#[derive(Deserialize)]
+struct SubscribeQuery {
+    token: String,
+}
+
+fn render(template: &impl Template, status: StatusCode) -> Result<HttpResponse, AppError> {
+    match template.render() {
+        Ok(html) => Ok(HttpResponse::build(status)
+            .insert_header(ContentType::html())
+            .body(html)),
+        Err(err) => Err(AppError::internal_server_error(err.into())),
+    }
+}
+
+fn not_found() -> Result<HttpResponse, AppError> {
+    render(&NotFoundTemplate, StatusCode::NOT_FOUND)
+}
+
+/// Loads everything the article page needs, or `None` when the article does
+/// not exist.
+async fn article_template(
+    database_connection: &sea_orm::DatabaseConnection,
+    article_id: i32,
+    comment_body: String,
+    error: Option<String>,
+) -> Result<Option<ArticleTemplate>, AppError> {
+    let articles_repository = repository::ArticlesRepository::new(database_connection.clone());
+    let comments_repository = repository::CommentsRepository::new(database_connection.clone());
+
+    let article = match articles_repository.find_by_id(article_id).await {
+        Ok(Some(article)) => article,
+        Ok(None) => return Ok(None),
+        Err(err) => return Err(AppError::internal_server_error(err.into())),
+    };
+
+    let tags = match articles_repository.find_tags(&article).await {
+        Ok(tags) => tags.into_iter().map(|tag| tag.name).collect(),
+        Err(err) => return Err(AppError::internal_server_error(err.into())),
+    };
+
+    let comments = match comments_repository
+        .find_approved_by_article_id(article_id)
+        .await
+    {
+        Ok(comments) => comments,
+        Err(err) => return Err(AppError::internal_server_error(err.into())),
+    };
+
+    let body_html = article
+        .body_html
+        .clone()
+        .unwrap_or_else(|| markdown::render_html(&article.body));
+
+    Ok(Some(ArticleTemplate {
+        body_html,
+        tags,
+        comments,
+        comments_path: comments_path(article.id),
+        comment_body,
+        error,
+        notice: None,
+        article,
+    }))
+}
+
+#[get("/")]
+async fn articles_index(data: web::Data<super::AppState>) -> Result<HttpResponse, AppError> {
+    let database_connection = &data.database_connection;
+
+    let articles_repository = repository::ArticlesRepository::new(database_connection.clone());
+
+    match articles_repository.find_all_with_tags().await {
+        Ok(articles) => {
+            let articles = articles
+                .into_iter()
+                .rev()
+                .map(|(article, tags)| ArticleSummary {
+                    path: article_path(article.id),
+                    title: article.title,
+                    created_at: article.created_at,
+                    tags: tags.into_iter().map(|tag| tag.name).collect(),
+                })
+                .collect();
+
+            render(&IndexTemplate { articles }, StatusCode::OK)
+        }
+        Err(err) => Err(AppError::internal_server_error(err.into())),
+    }
+}
+
+#[get("/posts/{id}")]
+async fn articles_show(
+    data: web::Data<super::AppState>,
+    id: web::Path<i32>,
+    query: web::Query<ArticleQuery>,
+) -> Result<HttpResponse, AppError> {
+    let id = id.into_inner();
+
+    match article_template(&data.database_connection, id, String::new(), None).await? {
+        Some(mut template) => {
+            if query.comment.as_deref() == Some("pending") {
+                template.notice =
+                    Some("Thanks! Your comment will appear once it has been approved.".to_string());
+            }
+            render(&template, StatusCode::OK)
+        }
+        None => not_found(),
+    }
+}
+
+#[post("/posts/{id}/comments")]
+async fn comments_create(
+    data: web::Data<super::AppState>,
+    id: web::Path<i32>,
+    comment_form: web::Form<CommentForm>,
+) -> Result<HttpResponse, AppError> {
+    let article_id = id.into_inner();
+    let comment_form = comment_form.into_inner();
+    let database_connection = &data.database_connection;
+
+    if comment_form.body.trim().is_empty() {
+        return match article_template(
+            database_connection,
+            article_id,
+            comment_form.body,
+            Some("Comment must not be empty.".to_string()),
+        )
+        .await?
+        {
+            Some(template) => render(&template, StatusCode::UNPROCESSABLE_ENTITY),
+            None => not_found(),
+        };
+    }
+
+    let articles_repository = repository::ArticlesRepository::new(database_connection.clone());
+
+    match articles_repository.find_by_id(article_id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return not_found(),
+        Err(err) => return Err(AppError::internal_server_error(err.into())),
+    }
+
+    let comments_repository = repository::CommentsRepository::new(database_connection.clone());
+
+    let candidate = spam::CommentCandidate {
+        article_id,
+        body: &comment_form.body,
+    };
+
+    let moderation_status = match data
+        .spam_pipeline
+        .initial_moderation_status(database_connection, &candidate)
+        .await
+    {
+        Ok(moderation_status) => moderation_status,
+        Err(err) => return Err(AppError::internal_server_error(err.into())),
+    };
+
+    let form = entity::comments::Model {
+        id: 0,
+        article_id,
+        body: comment_form.body,
+        created_at: Utc::now(),
+        updated_at: Utc::now(),
+        parent_id: None,
+        moderation_status,
+        moderated_at: None,
+    };
+
+    match comments_repository.create(form).await {
+        Ok(comment) => {
+            data.metrics.comment_created(moderation_status);
+            if let Ok(comment) = comment.clone().try_into_model() {
+                data.comment_hub.saved(&comment, None);
+                data.comment_notifier.saved(&comment, None);
+                data.webhooks
+                    .comment(webhook::WebhookEvent::CommentCreated, &comment);
+            }
+            let location = match moderation_status {
+                ModerationStatus::Approved => format!(
+                    "{}#comment-{}",
+                    article_path(article_id),
+                    comment.id.unwrap()
+                ),
+                _ => format!("{}?comment=pending#comments", article_path(article_id)),
+            };
+
+            Ok(HttpResponse::SeeOther()
+                .insert_header((header::LOCATION, location))
+                .finish())
+        }
+        Err(err) => Err(AppError::internal_server_error(err.into())),
+    }
+}
+
+/// Checks an unsubscribe token and builds the page for it, or returns `None`
+/// when the token is invalid or its article is gone.
+async fn unsubscribe_template(
+    data: &super::AppState,
+    token: &str,
+    unsubscribed: bool,
+) -> Result<Option<UnsubscribeTemplate>, AppError> {
+    let (article_id, email) = match data.comment_notifier.verify_unsubscribe_token(token) {
+        Some(verified) => verified,
+        None => return Ok(None),
+    };
+
+    let articles_repository = repository::ArticlesRepository::new(data.database_connection.clone());
+    let comment_subscriptions_repository =
+        repository::CommentSubscriptionsRepository::new(data.database_connection.clone());
+
+    let article = match articles_repository.find_by_id(article_id).await {
+        Ok(Some(article)) => article,
+        Ok(None) => return Ok(None),
+        Err(err) => return Err(AppError::internal_server_error(err.into())),
+    };
+
+    if unsubscribed {
+        if let Err(err) = comment_subscriptions_repository
+            .unsubscribe(article_id, &email)
+            .await
+        {
+            return Err(AppError::internal_server_error(err.into()));
+        }
+    }
+
+    Ok(Some(UnsubscribeTemplate {
+        email,
+        article_title: article.title,
+        article_path: article_path(article.id),
+        action: format!("{UNSUBSCRIBE_PATH}?token={token}"),
+        unsubscribed,
+    }))
+}
+
+/// Asks for confirmation rather than unsubscribing right away, as link
+/// scanners follow the links in emails.
+#[get("/unsubscribe")]
+async fn unsubscribe_show(
+    data: web::Data<super::AppState>,
+    query: web::Query<UnsubscribeQuery>,
+) -> Result<HttpResponse, AppError> {
+    match unsubscribe_template(&data, &query.token, false).await? {
+        Some(template) => render(&template, StatusCode::OK),
+        None => not_found(),
+    }
+}
+
+/// Also the target of one-click unsubscribes from mail clients, which POST
+/// `List-Unsubscribe=One-Click` to the link in the `List-Unsubscribe` header.
+#[post("/unsubscribe")]
+async fn unsubscribe_create(
+    data: web::Data<super::AppState>,
+    query: web::Query<UnsubscribeQuery>,
+) -> Result<HttpResponse, AppError> {
+    match unsubscribe_template(&data, &query.token, true).await? {
+        Some(template) => render(&template, StatusCode::OK),
+        None => not_found(),
+    }
+}
+
+/// Checks a subscription confirmation token and builds the page for it, or
+/// returns `None` when the token is invalid or its article is gone.
+async fn subscribe_template(
+    data: &super::AppState,
+    token: &str,
+    subscribed: bool,
+) -> Result<Option<SubscribeTemplate>, AppError> {
+    let (article_id, email) = match data.comment_notifier.verify_subscribe_token(token) {
+        Some(verified) => verified,
+        None => return Ok(None),
+    };
+
+    let articles_repository = repository::ArticlesRepository::new(data.database_connection.clone());
+    let comment_subscriptions_repository =
+        repository::CommentSubscriptionsRepository::new(data.database_connection.clone());
+
+    let article = match articles_repository.find_by_id(article_id).await {
+        Ok(Some(article)) => article,
+        Ok(None) => return Ok(None),
+        Err(err) => return Err(AppError::internal_server_error(err.into())),
+    };
+
+    if subscribed {
+        if let Err(err) = comment_subscriptions_repository
+            .subscribe(article_id, &email)
+            .await
+        {
+            return Err(AppError::internal_server_error(err.into()));
+        }
+    }
+
+    Ok(Some(SubscribeTemplate {
+        email,
+        article_title: article.title,
+        article_path: article_path(article.id),
+        action: format!("{SUBSCRIBE_PATH}?token={token}"),
+        subscribed,
+    }))
+}
+
+/// Asks for confirmation rather than subscribing right away, for the same
+/// reason as [`unsubscribe_show`].
+#[get("/subscribe")]
+async fn subscribe_show(
+    data: web::Data<super::AppState>,
+    query: web::Query<SubscribeQuery>,
+) -> Result<HttpResponse, AppError> {
+    match subscribe_template(&data, &query.token, false).await? {
+        Some(template) => render(&template, StatusCode::OK),
+        None => not_found(),
+    }
+}
+
+#[post("/subscribe")]
+async fn subscribe_create(
+    data: web::Data<super::AppState>,
+    query: web::Query<SubscribeQuery>,
+) -> Result<HttpResponse, AppError> {
+    match subscribe_template(&data, &query.token, true).await? {
+        Some(template) => render(&template, StatusCode::OK),
+        None => not_found(),
+    }
+}
@@ -1,28 +1,144 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use futures_util::Stream;
+
+use crate::{cache::ArticleCache, jobs, markdown};
+use entity::sea_orm_active_enums::{JobStatus, ModerationStatus, WebhookDeliveryStatus};
+
 use sea_orm::{
-    ActiveModelTrait, ColumnTrait, DatabaseConnection, DbErr, EntityTrait, ModelTrait, QueryFilter,
-    Set,
+    sea_query::{Expr, LockBehavior, LockType},
+    ActiveModelTrait, ColumnTrait, Condition, ConnectionTrait, DatabaseConnection,
+    DatabaseTransaction, DbErr, EntityTrait, FromQueryResult, ModelTrait, PaginatorTrait,
+    QueryFilter, QueryOrder, QuerySelect, RelationTrait, Set, TransactionTrait,
 };
 
 pub struct ArticlesRepository {
     pub database_connection: DatabaseConnection,
+    cache: ArticleCache,
 }
 
 impl ArticlesRepository {
     pub fn new(database_connection: DatabaseConnection) -> Self {
+        Self::cached(database_connection, ArticleCache::disabled())
+    }
+
+    /// Reads articles through `cache`, and drops what writes change from it.
+    /// Writes must go through a cached repository for the cache to stay
+    /// accurate. Only cache misses open the instrumented spans, so that hits
+    /// are not recorded as database queries.
+    pub fn cached(database_connection: DatabaseConnection, cache: ArticleCache) -> Self {
         Self {
             database_connection,
+            cache,
         }
     }
 
-    pub async fn find_all(&self) -> Result<Vec<entity::articles::Model>, DbErr> {
-        let articles = entity::articles::Entity::find()
+    pub async fn find_all_with_tags(
+        &self,
+    ) -> Result<Vec<(entity::articles::Model, Vec<entity::tags::Model>)>, DbErr> {
+        self.cache.list(None, self.query_find_all_with_tags()).await
+    }
+
+    #[tracing::instrument(
+        name = "ArticlesRepository::find_all_with_tags",
+        level = "debug",
+        skip(self),
+        err
+    )]
+    async fn query_find_all_with_tags(
+        &self,
+    ) -> Result<Vec<(entity::articles::Model, Vec<entity::tags::Model>)>, DbErr> {
+        let rows = entity::articles::Entity::find()
+            .find_also_linked(entity::articles::ArticleToTag)
+            .order_by_asc(entity::articles::Column::Id)
             .all(&self.database_connection)
             .await?;
 
-        Ok(articles)
+        Ok(group_tags_by_article(rows))
+    }
+
+    pub async fn find_all_with_tags_by_tag_name(
+        &self,
+        tag_name: &str,
+    ) -> Result<Vec<(entity::articles::Model, Vec<entity::tags::Model>)>, DbErr> {
+        self.cache
+            .list(
+                Some(tag_name),
+                self.query_find_all_with_tags_by_tag_name(tag_name),
+            )
+            .await
+    }
+
+    #[tracing::instrument(
+        name = "ArticlesRepository::find_all_with_tags_by_tag_name",
+        level = "debug",
+        skip(self),
+        err
+    )]
+    async fn query_find_all_with_tags_by_tag_name(
+        &self,
+        tag_name: &str,
+    ) -> Result<Vec<(entity::articles::Model, Vec<entity::tags::Model>)>, DbErr> {
+        let tag = entity::tags::Entity::find()
+            .filter(entity::tags::Column::Name.eq(tag_name))
+            .one(&self.database_connection)
+            .await?;
+
+        let tag = match tag {
+            Some(tag) => tag,
+            None => return Ok(vec![]),
+        };
+
+        let article_ids = tag
+            .find_linked(entity::tags::TagToArticle)
+            .all(&self.database_connection)
+            .await?
+            .into_iter()
+            .map(|article| article.id)
+            .collect::<Vec<i32>>();
+
+        let rows = entity::articles::Entity::find()
+            .filter(entity::articles::Column::Id.is_in(article_ids))
+            .find_also_linked(entity::articles::ArticleToTag)
+            .order_by_asc(entity::articles::Column::Id)
+            .all(&self.database_connection)
+            .await?;
+
+        Ok(group_tags_by_article(rows))
+    }
+
+    #[tracing::instrument(
+        name = "ArticlesRepository::find_all_with_tags_by_category_ids",
+        level = "debug",
+        skip(self),
+        err
+    )]
+    pub async fn find_all_with_tags_by_category_ids(
+        &self,
+        category_ids: Vec<i32>,
+    ) -> Result<Vec<(entity::articles::Model, Vec<entity::tags::Model>)>, DbErr> {
+        let rows = entity::articles::Entity::find()
+            .filter(entity::articles::Column::CategoryId.is_in(category_ids))
+            .find_also_linked(entity::articles::ArticleToTag)
+            .order_by_asc(entity::articles::Column::Id)
+            .all(&self.database_connection)
+            .await?;
+
+        Ok(group_tags_by_article(rows))
     }
 
     pub async fn find_by_id(&self, id: i32) -> Result<Option<entity::articles::Model>, DbErr> {
+        self.cache.article(id, self.query_find_by_id(id)).await
+    }
+
+    #[tracing::instrument(
+        name = "ArticlesRepository::find_by_id",
+        level = "debug",
+        skip(self),
+        err
+    )]
+    async fn query_find_by_id(&self, id: i32) -> Result<Option<entity::articles::Model>, DbErr> {
         let article = entity::articles::Entity::find_by_id(id)
             .one(&self.database_connection)
             .await?;
@@ -30,40 +146,196 @@ impl ArticlesRepository {
         Ok(article)
     }
 
+    #[tracing::instrument(
+        name = "ArticlesRepository::find_latest",
+        level = "debug",
+        skip(self),
+        err
+    )]
+    pub async fn find_latest(&self, limit: u64) -> Result<Vec<entity::articles::Model>, DbErr> {
+        let articles = entity::articles::Entity::find()
+            .order_by_desc(entity::articles::Column::CreatedAt)
+            .order_by_desc(entity::articles::Column::Id)
+            .limit(limit)
+            .all(&self.database_connection)
+            .await?;
+
+        Ok(articles)
+    }
+
+    #[tracing::instrument(name = "ArticlesRepository::count", level = "debug", skip(self), err)]
+    pub async fn count(&self) -> Result<u64, DbErr> {
+        let count = entity::articles::Entity::find()
+            .count(&self.database_connection)
+            .await?;
+
+        Ok(count)
+    }
+
+    /// Streams the id and modification time of the articles in `offset..offset + limit`
+    /// without loading the whole page into memory.
+    #[tracing::instrument(
+        name = "ArticlesRepository::stream_sitemap_entries",
+        level = "debug",
+        skip(self),
+        err
+    )]
+    pub async fn stream_sitemap_entries(
+        &self,
+        offset: u64,
+        limit: u64,
+    ) -> Result<impl Stream<Item = Result<ArticleSitemapEntry, DbErr>> + '_, DbErr> {
+        let entries = entity::articles::Entity::find()
+            .select_only()
+            .column(entity::articles::Column::Id)
+            .column(entity::articles::Column::UpdatedAt)
+            .order_by_asc(entity::articles::Column::Id)
+            .offset(offset)
+            .limit(limit)
+            .into_model::<ArticleSitemapEntry>()
+            .stream(&self.database_connection)
+            .await?;
+
+        Ok(entries)
+    }
+
+    pub async fn find_tags(
+        &self,
+        article: &entity::articles::Model,
+    ) -> Result<Vec<entity::tags::Model>, DbErr> {
+        self.cache
+            .tags(article.id, self.query_find_tags(article))
+            .await
+    }
+
+    #[tracing::instrument(
+        name = "ArticlesRepository::find_tags",
+        level = "debug",
+        skip(self, article),
+        fields(article_id = article.id),
+        err
+    )]
+    async fn query_find_tags(
+        &self,
+        article: &entity::articles::Model,
+    ) -> Result<Vec<entity::tags::Model>, DbErr> {
+        let tags = article
+            .find_linked(entity::articles::ArticleToTag)
+            .order_by_asc(entity::tags::Column::Name)
+            .all(&self.database_connection)
+            .await?;
+
+        Ok(tags)
+    }
+
+    /// Creates an article tagged with `tag_names`, as
+    /// [`TagsRepository::replace_for_article`] would tag it, in one
+    /// transaction.
+    #[tracing::instrument(
+        name = "ArticlesRepository::create",
+        level = "debug",
+        skip(self, form_data),
+        err
+    )]
     pub async fn create(
         &self,
         form_data: entity::articles::Model,
-    ) -> Result<entity::articles::ActiveModel, DbErr> {
+        tag_names: &[String],
+    ) -> Result<(entity::articles::ActiveModel, Vec<entity::tags::Model>), DbErr> {
+        let transaction = self.database_connection.begin().await?;
+
         let article = entity::articles::ActiveModel {
             title: Set(form_data.title.to_owned()),
             body: Set(form_data.body.to_owned()),
+            category_id: Set(form_data.category_id),
+            body_html: Set(Some(markdown::render_html(&form_data.body))),
+            created_at: Set(Utc::now()),
+            updated_at: Set(Utc::now()),
+            author_email: Set(form_data.author_email.to_owned()),
             ..Default::default()
         }
-        .save(&self.database_connection)
+        .save(&transaction)
         .await?;
 
-        Ok(article)
+        insert_next_revision(
+            &transaction,
+            article.id.clone().unwrap(),
+            &form_data.title,
+            &form_data.body,
+        )
+        .await?;
+
+        let tags =
+            replace_article_tags(&transaction, article.id.clone().unwrap(), tag_names).await?;
+
+        transaction.commit().await?;
+        self.cache.article_changed(article.id.clone().unwrap());
+
+        Ok((article, tags))
     }
 
+    #[tracing::instrument(
+        name = "ArticlesRepository::update",
+        level = "debug",
+        skip(self, form_data),
+        fields(id = form_data.id),
+        err
+    )]
     pub async fn update(
         &self,
         form_data: entity::articles::Model,
     ) -> Result<entity::articles::ActiveModel, DbErr> {
+        let transaction = self.database_connection.begin().await?;
+
+        // Locked until the transaction ends, so that concurrent updates of the
+        // article take turns numbering their revisions.
         let article = entity::articles::Entity::find_by_id(form_data.id)
-            .one(&self.database_connection)
+            .lock_exclusive()
+            .one(&transaction)
             .await?;
 
-        let mut article: entity::articles::ActiveModel = article.unwrap().into();
+        let article = article.unwrap();
+
+        // Articles written before revisions were recorded have no history yet, so
+        // keep their current content as the first revision before replacing it.
+        let has_revisions = entity::article_revisions::Entity::find()
+            .filter(entity::article_revisions::Column::ArticleId.eq(article.id))
+            .count(&transaction)
+            .await?
+            > 0;
+        if !has_revisions {
+            insert_next_revision(&transaction, article.id, &article.title, &article.body).await?;
+        }
+
+        let body_changed = article.body != form_data.body || article.body_html.is_none();
+        let mut article: entity::articles::ActiveModel = article.into();
 
         article.title = Set(form_data.title.to_owned());
         article.body = Set(form_data.body.to_owned());
+        article.category_id = Set(form_data.category_id);
+        article.author_email = Set(form_data.author_email.to_owned());
+        if body_changed {
+            article.body_html = Set(Some(markdown::render_html(&form_data.body)));
+        }
+        article.updated_at = Set(Utc::now());
+
+        let article: entity::articles::ActiveModel = article.update(&transaction).await?.into();
+
+        insert_next_revision(
+            &transaction,
+            form_data.id,
+            &form_data.title,
+            &form_data.body,
+        )
+        .await?;
 
-        let article: entity::articles::ActiveModel =
-            article.update(&self.database_connection).await?.into();
+        transaction.commit().await?;
+        self.cache.article_changed(form_data.id);
 
         Ok(article)
     }
 
+    #[tracing::instrument(name = "ArticlesRepository::delete", level = "debug", skip(self), err)]
     pub async fn delete(&self, id: i32) -> Result<sea_orm::DeleteResult, DbErr> {
         let article = entity::articles::Entity::find_by_id(id)
             .one(&self.database_connection)
@@ -71,90 +343,1724 @@ impl ArticlesRepository {
 
         let article: entity::articles::Model = article.unwrap();
         let res: sea_orm::DeleteResult = article.delete(&self.database_connection).await?;
+        self.cache.article_changed(id);
 
         Ok(res)
     }
 }
 
-pub struct CommentsRepository {
+/// Records `title` and `body` as the next revision of an article. Revisions are
+/// numbered from 1 per article. Callers updating an existing article must hold
+/// a lock on its row, or two revisions could get the same number.
+async fn insert_next_revision<C: ConnectionTrait>(
+    db: &C,
+    article_id: i32,
+    title: &str,
+    body: &str,
+) -> Result<entity::article_revisions::Model, DbErr> {
+    let latest = entity::article_revisions::Entity::find()
+        .filter(entity::article_revisions::Column::ArticleId.eq(article_id))
+        .order_by_desc(entity::article_revisions::Column::Revision)
+        .one(db)
+        .await?;
+
+    let revision = entity::article_revisions::ActiveModel {
+        article_id: Set(article_id),
+        revision: Set(latest.map_or(1, |latest| latest.revision + 1)),
+        title: Set(title.to_owned()),
+        body: Set(body.to_owned()),
+        created_at: Set(Utc::now()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await?;
+
+    Ok(revision)
+}
+
+pub struct ArticleRevisionsRepository {
     pub database_connection: DatabaseConnection,
 }
 
-impl CommentsRepository {
+impl ArticleRevisionsRepository {
     pub fn new(database_connection: DatabaseConnection) -> Self {
         Self {
             database_connection,
         }
     }
 
+    #[tracing::instrument(
+        name = "ArticleRevisionsRepository::find_all_by_article_id",
+        level = "debug",
+        skip(self),
+        err
+    )]
     pub async fn find_all_by_article_id(
         &self,
         article_id: i32,
-    ) -> Result<Vec<entity::comments::Model>, DbErr> {
-        let comments = entity::comments::Entity::find()
-            .filter(entity::comments::Column::ArticleId.eq(article_id))
+    ) -> Result<Vec<entity::article_revisions::Model>, DbErr> {
+        let revisions = entity::article_revisions::Entity::find()
+            .filter(entity::article_revisions::Column::ArticleId.eq(article_id))
+            .order_by_desc(entity::article_revisions::Column::Revision)
             .all(&self.database_connection)
             .await?;
 
-        Ok(comments)
+        Ok(revisions)
     }
 
-    pub async fn find_by_article_id_and_id(
+    #[tracing::instrument(
+        name = "ArticleRevisionsRepository::find_by_article_id_and_revision",
+        level = "debug",
+        skip(self),
+        err
+    )]
+    pub async fn find_by_article_id_and_revision(
         &self,
         article_id: i32,
-        id: i32,
-    ) -> Result<Option<entity::comments::Model>, DbErr> {
-        let comment = entity::comments::Entity::find_by_id(id)
-            .filter(entity::comments::Column::ArticleId.eq(article_id))
+        revision: i32,
+    ) -> Result<Option<entity::article_revisions::Model>, DbErr> {
+        let revision = entity::article_revisions::Entity::find()
+            .filter(entity::article_revisions::Column::ArticleId.eq(article_id))
+            .filter(entity::article_revisions::Column::Revision.eq(revision))
             .one(&self.database_connection)
             .await?;
 
-        Ok(comment)
+        Ok(revision)
+    }
+}
+
+#[derive(Debug, FromQueryResult)]
+pub struct ArticleSitemapEntry {
+    pub id: i32,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Collapses the rows of an `articles LEFT JOIN tags` query into one entry per
+/// article, keeping the order in which the articles were returned.
+fn group_tags_by_article(
+    rows: Vec<(entity::articles::Model, Option<entity::tags::Model>)>,
+) -> Vec<(entity::articles::Model, Vec<entity::tags::Model>)> {
+    let mut grouped: Vec<(entity::articles::Model, Vec<entity::tags::Model>)> = vec![];
+
+    for (article, tag) in rows {
+        match grouped.last_mut() {
+            Some((last, tags)) if last.id == article.id => tags.extend(tag),
+            _ => grouped.push((article, tag.into_iter().collect())),
+        }
+    }
+
+    for (_, tags) in grouped.iter_mut() {
+        tags.sort_by(|a, b| a.name.cmp(&b.name));
+    }
+
+    grouped
+}
+
+#[derive(Debug, FromQueryResult)]
+pub struct TagWithArticleCount {
+    pub id: i32,
+    pub name: String,
+    pub article_count: i64,
+}
+
+pub struct TagsRepository {
+    pub database_connection: DatabaseConnection,
+    cache: ArticleCache,
+}
+
+impl TagsRepository {
+    pub fn new(database_connection: DatabaseConnection) -> Self {
+        Self::cached(database_connection, ArticleCache::disabled())
+    }
+
+    /// Drops the articles whose tags change from `cache`, like
+    /// [`ArticlesRepository::cached`].
+    pub fn cached(database_connection: DatabaseConnection, cache: ArticleCache) -> Self {
+        Self {
+            database_connection,
+            cache,
+        }
+    }
+
+    #[tracing::instrument(
+        name = "TagsRepository::find_all_with_article_count",
+        level = "debug",
+        skip(self),
+        err
+    )]
+    pub async fn find_all_with_article_count(&self) -> Result<Vec<TagWithArticleCount>, DbErr> {
+        let tags = entity::tags::Entity::find()
+            .select_only()
+            .column(entity::tags::Column::Id)
+            .column(entity::tags::Column::Name)
+            .column_as(
+                entity::article_tags::Column::ArticleId.count(),
+                "article_count",
+            )
+            .join(
+                sea_orm::JoinType::LeftJoin,
+                entity::tags::Relation::ArticleTags.def(),
+            )
+            .group_by(entity::tags::Column::Id)
+            .group_by(entity::tags::Column::Name)
+            .order_by_asc(entity::tags::Column::Name)
+            .into_model::<TagWithArticleCount>()
+            .all(&self.database_connection)
+            .await?;
+
+        Ok(tags)
+    }
+
+    /// Replaces the tags of an article with `names`, creating any tag that does
+    /// not exist yet. Names are trimmed, blank names are ignored, and names that
+    /// differ only in case are the same tag, spelled as first created.
+    #[tracing::instrument(
+        name = "TagsRepository::replace_for_article",
+        level = "debug",
+        skip(self),
+        err
+    )]
+    pub async fn replace_for_article(
+        &self,
+        article_id: i32,
+        names: &[String],
+    ) -> Result<Vec<entity::tags::Model>, DbErr> {
+        let transaction = self.database_connection.begin().await?;
+
+        let tags = replace_article_tags(&transaction, article_id, names).await?;
+
+        transaction.commit().await?;
+        self.cache.article_changed(article_id);
+
+        Ok(tags)
+    }
+}
+
+/// Replaces the tags of an article within `db`, for
+/// [`TagsRepository::replace_for_article`] and article creation.
+async fn replace_article_tags<C: ConnectionTrait>(
+    db: &C,
+    article_id: i32,
+    names: &[String],
+) -> Result<Vec<entity::tags::Model>, DbErr> {
+    let mut names = names
+        .iter()
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .collect::<Vec<String>>();
+    names.sort_by_key(|name| name.to_lowercase());
+    names.dedup_by_key(|name| name.to_lowercase());
+
+    // The collation compares names case-insensitively, so "rust" finds an
+    // existing "Rust", and inserting it as well would break the unique
+    // index.
+    let mut tags = entity::tags::Entity::find()
+        .filter(entity::tags::Column::Name.is_in(names.clone()))
+        .all(db)
+        .await?;
+
+    let existing_names = tags
+        .iter()
+        .map(|tag| (tag.name.to_lowercase(), tag.id))
+        .collect::<HashMap<String, i32>>();
+
+    for name in names.iter() {
+        if existing_names.contains_key(&name.to_lowercase()) {
+            continue;
+        }
+
+        let tag = entity::tags::ActiveModel {
+            name: Set(name.to_owned()),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?;
+
+        tags.push(tag);
+    }
+
+    entity::article_tags::Entity::delete_many()
+        .filter(entity::article_tags::Column::ArticleId.eq(article_id))
+        .exec(db)
+        .await?;
+
+    if !tags.is_empty() {
+        entity::article_tags::Entity::insert_many(tags.iter().map(|tag| {
+            entity::article_tags::ActiveModel {
+                article_id: Set(article_id),
+                tag_id: Set(tag.id),
+            }
+        }))
+        .exec(db)
+        .await?;
+    }
+
+    tags.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(tags)
+}
+
+pub struct CategoriesRepository {
+    pub database_connection: DatabaseConnection,
+}
+
+impl CategoriesRepository {
+    pub const DEFAULT_NAME: &'static str = "Uncategorized";
+
+    pub fn new(database_connection: DatabaseConnection) -> Self {
+        Self {
+            database_connection,
+        }
+    }
+
+    #[tracing::instrument(
+        name = "CategoriesRepository::find_all",
+        level = "debug",
+        skip(self),
+        err
+    )]
+    pub async fn find_all(&self) -> Result<Vec<entity::categories::Model>, DbErr> {
+        let categories = entity::categories::Entity::find()
+            .order_by_asc(entity::categories::Column::Id)
+            .all(&self.database_connection)
+            .await?;
+
+        Ok(categories)
+    }
+
+    #[tracing::instrument(
+        name = "CategoriesRepository::find_by_id",
+        level = "debug",
+        skip(self),
+        err
+    )]
+    pub async fn find_by_id(&self, id: i32) -> Result<Option<entity::categories::Model>, DbErr> {
+        let category = entity::categories::Entity::find_by_id(id)
+            .one(&self.database_connection)
+            .await?;
+
+        Ok(category)
+    }
+
+    /// Returns `id` followed by the ids of every category below it in the tree.
+    #[tracing::instrument(
+        name = "CategoriesRepository::find_self_and_descendant_ids",
+        level = "debug",
+        skip(self),
+        err
+    )]
+    pub async fn find_self_and_descendant_ids(&self, id: i32) -> Result<Vec<i32>, DbErr> {
+        let categories = self.find_all().await?;
+
+        let mut ids = vec![id];
+        let mut index = 0;
+        while index < ids.len() {
+            let parent_id = ids[index];
+            ids.extend(
+                categories
+                    .iter()
+                    .filter(|category| category.parent_id == Some(parent_id))
+                    .map(|category| category.id),
+            );
+            index += 1;
+        }
+
+        Ok(ids)
     }
 
+    #[tracing::instrument(
+        name = "CategoriesRepository::count_articles",
+        level = "debug",
+        skip(self),
+        err
+    )]
+    pub async fn count_articles(&self, id: i32) -> Result<u64, DbErr> {
+        let count = entity::articles::Entity::find()
+            .filter(entity::articles::Column::CategoryId.eq(id))
+            .count(&self.database_connection)
+            .await?;
+
+        Ok(count)
+    }
+
+    #[tracing::instrument(
+        name = "CategoriesRepository::count_children",
+        level = "debug",
+        skip(self),
+        err
+    )]
+    pub async fn count_children(&self, id: i32) -> Result<u64, DbErr> {
+        let count = entity::categories::Entity::find()
+            .filter(entity::categories::Column::ParentId.eq(id))
+            .count(&self.database_connection)
+            .await?;
+
+        Ok(count)
+    }
+
+    #[tracing::instrument(
+        name = "CategoriesRepository::create",
+        level = "debug",
+        skip(self, form_data),
+        err
+    )]
     pub async fn create(
         &self,
-        form_data: entity::comments::Model,
-    ) -> Result<entity::comments::ActiveModel, DbErr> {
-        let comment = entity::comments::ActiveModel {
-            article_id: Set(form_data.article_id.to_owned()),
-            body: Set(form_data.body.to_owned()),
+        form_data: entity::categories::Model,
+    ) -> Result<entity::categories::ActiveModel, DbErr> {
+        let category = entity::categories::ActiveModel {
+            parent_id: Set(form_data.parent_id),
+            name: Set(form_data.name.to_owned()),
             ..Default::default()
         }
         .save(&self.database_connection)
         .await?;
 
-        Ok(comment)
+        Ok(category)
+    }
+
+    /// The root category articles are filed under when none is given,
+    /// created the first time it is needed.
+    #[tracing::instrument(
+        name = "CategoriesRepository::find_or_create_default",
+        level = "debug",
+        skip(self),
+        err
+    )]
+    pub async fn find_or_create_default(&self) -> Result<entity::categories::Model, DbErr> {
+        let category = entity::categories::Entity::find()
+            .filter(entity::categories::Column::ParentId.is_null())
+            .filter(entity::categories::Column::Name.eq(Self::DEFAULT_NAME))
+            .order_by_asc(entity::categories::Column::Id)
+            .one(&self.database_connection)
+            .await?;
+
+        match category {
+            Some(category) => Ok(category),
+            None => {
+                entity::categories::ActiveModel {
+                    parent_id: Set(None),
+                    name: Set(Self::DEFAULT_NAME.to_string()),
+                    ..Default::default()
+                }
+                .insert(&self.database_connection)
+                .await
+            }
+        }
     }
 
+    #[tracing::instrument(
+        name = "CategoriesRepository::update",
+        level = "debug",
+        skip(self, form_data),
+        fields(id = form_data.id),
+        err
+    )]
     pub async fn update(
         &self,
-        form_data: entity::comments::Model,
-    ) -> Result<entity::comments::ActiveModel, DbErr> {
-        let comment = entity::comments::Entity::find_by_id(form_data.id)
-            .filter(entity::comments::Column::ArticleId.eq(form_data.article_id))
+        form_data: entity::categories::Model,
+    ) -> Result<entity::categories::ActiveModel, DbErr> {
+        let category = entity::categories::Entity::find_by_id(form_data.id)
             .one(&self.database_connection)
             .await?;
 
-        let mut comment: entity::comments::ActiveModel = comment.unwrap().into();
+        let mut category: entity::categories::ActiveModel = category.unwrap().into();
 
-        comment.body = Set(form_data.body.to_owned());
+        category.parent_id = Set(form_data.parent_id);
+        category.name = Set(form_data.name.to_owned());
 
-        let comment: entity::comments::ActiveModel =
-            comment.update(&self.database_connection).await?.into();
+        let category: entity::categories::ActiveModel =
+            category.update(&self.database_connection).await?.into();
 
-        Ok(comment)
+        Ok(category)
     }
 
-    pub async fn delete(&self, article_id: i32, id: i32) -> Result<sea_orm::DeleteResult, DbErr> {
-        let comment = entity::comments::Entity::find_by_id(id)
-            .filter(entity::comments::Column::ArticleId.eq(article_id))
+    #[tracing::instrument(
+        name = "CategoriesRepository::delete",
+        level = "debug",
+        skip(self),
+        err
+    )]
+    pub async fn delete(&self, id: i32) -> Result<sea_orm::DeleteResult, DbErr> {
+        let category = entity::categories::Entity::find_by_id(id)
             .one(&self.database_connection)
             .await?;
 
-        let comment: entity::comments::Model = comment.unwrap();
-        let res: sea_orm::DeleteResult = comment.delete(&self.database_connection).await?;
+        let category: entity::categories::Model = category.unwrap();
+        let res: sea_orm::DeleteResult = category.delete(&self.database_connection).await?;
 
         Ok(res)
     }
 }
+
+pub struct CommentsRepository {
+    pub database_connection: DatabaseConnection,
+}
+
+impl CommentsRepository {
+    pub fn new(database_connection: DatabaseConnection) -> Self {
+        Self {
+            database_connection,
+        }
+    }
+
+    #[tracing::instrument(
+        name = "CommentsRepository::find_approved_by_article_id",
+        level = "debug",
+        skip(self),
+        err
+    )]
+    pub async fn find_approved_by_article_id(
+        &self,
+        article_id: i32,
+    ) -> Result<Vec<entity::comments::Model>, DbErr> {
+        let comments = entity::comments::Entity::find()
+            .filter(entity::comments::Column::ArticleId.eq(article_id))
+            .filter(entity::comments::Column::ModerationStatus.eq(ModerationStatus::Approved))
+            .order_by_asc(entity::comments::Column::Id)
+            .all(&self.database_connection)
+            .await?;
+
+        Ok(comments)
+    }
+
+    /// Approved comments on any of `article_ids`, in one query.
+    #[tracing::instrument(
+        name = "CommentsRepository::find_approved_by_article_ids",
+        level = "debug",
+        skip(self),
+        err
+    )]
+    pub async fn find_approved_by_article_ids(
+        &self,
+        article_ids: Vec<i32>,
+    ) -> Result<Vec<entity::comments::Model>, DbErr> {
+        let comments = entity::comments::Entity::find()
+            .filter(entity::comments::Column::ArticleId.is_in(article_ids))
+            .filter(entity::comments::Column::ModerationStatus.eq(ModerationStatus::Approved))
+            .order_by_asc(entity::comments::Column::Id)
+            .all(&self.database_connection)
+            .await?;
+
+        Ok(comments)
+    }
+
+    #[tracing::instrument(
+        name = "CommentsRepository::find_latest_approved_by_article_id",
+        level = "debug",
+        skip(self),
+        err
+    )]
+    pub async fn find_latest_approved_by_article_id(
+        &self,
+        article_id: i32,
+        limit: u64,
+    ) -> Result<Vec<entity::comments::Model>, DbErr> {
+        let comments = entity::comments::Entity::find()
+            .filter(entity::comments::Column::ArticleId.eq(article_id))
+            .filter(entity::comments::Column::ModerationStatus.eq(ModerationStatus::Approved))
+            .order_by_desc(entity::comments::Column::CreatedAt)
+            .order_by_desc(entity::comments::Column::Id)
+            .limit(limit)
+            .all(&self.database_connection)
+            .await?;
+
+        Ok(comments)
+    }
+
+    #[tracing::instrument(
+        name = "CommentsRepository::find_all_by_moderation_status",
+        level = "debug",
+        skip(self),
+        err
+    )]
+    pub async fn find_all_by_moderation_status(
+        &self,
+        moderation_status: ModerationStatus,
+    ) -> Result<Vec<entity::comments::Model>, DbErr> {
+        let comments = entity::comments::Entity::find()
+            .filter(entity::comments::Column::ModerationStatus.eq(moderation_status))
+            .order_by_asc(entity::comments::Column::CreatedAt)
+            .order_by_asc(entity::comments::Column::Id)
+            .all(&self.database_connection)
+            .await?;
+
+        Ok(comments)
+    }
+
+    /// Comments a moderator put in `moderation_status`, as opposed to those the
+    /// spam filters or auto-approval left there.
+    #[tracing::instrument(
+        name = "CommentsRepository::find_all_moderated_by_moderation_status",
+        level = "debug",
+        skip(self),
+        err
+    )]
+    pub async fn find_all_moderated_by_moderation_status(
+        &self,
+        moderation_status: ModerationStatus,
+    ) -> Result<Vec<entity::comments::Model>, DbErr> {
+        let comments = entity::comments::Entity::find()
+            .filter(entity::comments::Column::ModerationStatus.eq(moderation_status))
+            .filter(entity::comments::Column::ModeratedAt.is_not_null())
+            .order_by_asc(entity::comments::Column::Id)
+            .all(&self.database_connection)
+            .await?;
+
+        Ok(comments)
+    }
+
+    #[tracing::instrument(
+        name = "CommentsRepository::count_by_article_id_and_body_since",
+        level = "debug",
+        skip(self, body),
+        err
+    )]
+    pub async fn count_by_article_id_and_body_since(
+        &self,
+        article_id: i32,
+        body: &str,
+        since: DateTime<Utc>,
+    ) -> Result<u64, DbErr> {
+        let count = entity::comments::Entity::find()
+            .filter(entity::comments::Column::ArticleId.eq(article_id))
+            .filter(entity::comments::Column::Body.eq(body))
+            .filter(entity::comments::Column::CreatedAt.gte(since))
+            .count(&self.database_connection)
+            .await?;
+
+        Ok(count)
+    }
+
+    #[tracing::instrument(
+        name = "CommentsRepository::find_by_id",
+        level = "debug",
+        skip(self),
+        err
+    )]
+    pub async fn find_by_id(&self, id: i32) -> Result<Option<entity::comments::Model>, DbErr> {
+        let comment = entity::comments::Entity::find_by_id(id)
+            .one(&self.database_connection)
+            .await?;
+
+        Ok(comment)
+    }
+
+    #[tracing::instrument(
+        name = "CommentsRepository::find_by_article_id_and_id",
+        level = "debug",
+        skip(self),
+        err
+    )]
+    pub async fn find_by_article_id_and_id(
+        &self,
+        article_id: i32,
+        id: i32,
+    ) -> Result<Option<entity::comments::Model>, DbErr> {
+        let comment = entity::comments::Entity::find_by_id(id)
+            .filter(entity::comments::Column::ArticleId.eq(article_id))
+            .one(&self.database_connection)
+            .await?;
+
+        Ok(comment)
+    }
+
+    #[tracing::instrument(
+        name = "CommentsRepository::create",
+        level = "debug",
+        skip(self, form_data),
+        err
+    )]
+    pub async fn create(
+        &self,
+        form_data: entity::comments::Model,
+    ) -> Result<entity::comments::ActiveModel, DbErr> {
+        let comment = entity::comments::ActiveModel {
+            article_id: Set(form_data.article_id.to_owned()),
+            parent_id: Set(form_data.parent_id),
+            body: Set(form_data.body.to_owned()),
+            moderation_status: Set(form_data.moderation_status),
+            created_at: Set(Utc::now()),
+            updated_at: Set(Utc::now()),
+            ..Default::default()
+        }
+        .save(&self.database_connection)
+        .await?;
+
+        Ok(comment)
+    }
+
+    #[tracing::instrument(
+        name = "CommentsRepository::update",
+        level = "debug",
+        skip(self, form_data),
+        fields(id = form_data.id),
+        err
+    )]
+    pub async fn update(
+        &self,
+        form_data: entity::comments::Model,
+    ) -> Result<entity::comments::ActiveModel, DbErr> {
+        let comment = entity::comments::Entity::find_by_id(form_data.id)
+            .filter(entity::comments::Column::ArticleId.eq(form_data.article_id))
+            .one(&self.database_connection)
+            .await?;
+
+        let mut comment: entity::comments::ActiveModel = comment.unwrap().into();
+
+        comment.body = Set(form_data.body.to_owned());
+        comment.moderation_status = Set(form_data.moderation_status);
+        comment.moderated_at = Set(form_data.moderated_at);
+        comment.updated_at = Set(Utc::now());
+
+        let comment: entity::comments::ActiveModel =
+            comment.update(&self.database_connection).await?.into();
+
+        Ok(comment)
+    }
+
+    #[tracing::instrument(
+        name = "CommentsRepository::update_moderation_status",
+        level = "debug",
+        skip(self),
+        err
+    )]
+    pub async fn update_moderation_status(
+        &self,
+        id: i32,
+        moderation_status: ModerationStatus,
+    ) -> Result<entity::comments::ActiveModel, DbErr> {
+        let comment = entity::comments::Entity::find_by_id(id)
+            .one(&self.database_connection)
+            .await?;
+
+        let mut comment: entity::comments::ActiveModel = comment.unwrap().into();
+
+        comment.moderation_status = Set(moderation_status);
+        comment.moderated_at = Set(Some(Utc::now()));
+        comment.updated_at = Set(Utc::now());
+
+        let comment: entity::comments::ActiveModel =
+            comment.update(&self.database_connection).await?.into();
+
+        Ok(comment)
+    }
+
+    #[tracing::instrument(name = "CommentsRepository::delete", level = "debug", skip(self), err)]
+    pub async fn delete(&self, article_id: i32, id: i32) -> Result<sea_orm::DeleteResult, DbErr> {
+        let comment = entity::comments::Entity::find_by_id(id)
+            .filter(entity::comments::Column::ArticleId.eq(article_id))
+            .one(&self.database_connection)
+            .await?;
+
+        let comment: entity::comments::Model = comment.unwrap();
+        let res: sea_orm::DeleteResult = comment.delete(&self.database_connection).await?;
+
+        Ok(res)
+    }
+}
+
+pub struct CommentSubscriptionsRepository {
+    pub database_connection: DatabaseConnection,
+}
+
+impl CommentSubscriptionsRepository {
+    pub fn new(database_connection: DatabaseConnection) -> Self {
+        Self {
+            database_connection,
+        }
+    }
+
+    #[tracing::instrument(
+        name = "CommentSubscriptionsRepository::find_by_article_id_and_email",
+        level = "debug",
+        skip(self),
+        err
+    )]
+    pub async fn find_by_article_id_and_email(
+        &self,
+        article_id: i32,
+        email: &str,
+    ) -> Result<Option<entity::comment_subscriptions::Model>, DbErr> {
+        let subscription = entity::comment_subscriptions::Entity::find()
+            .filter(entity::comment_subscriptions::Column::ArticleId.eq(article_id))
+            .filter(entity::comment_subscriptions::Column::Email.eq(email))
+            .one(&self.database_connection)
+            .await?;
+
+        Ok(subscription)
+    }
+
+    /// Everyone to notify of new comments on `article`: its author and the
+    /// thread's subscribers, less whoever unsubscribed.
+    #[tracing::instrument(
+        name = "CommentSubscriptionsRepository::find_recipients",
+        level = "debug",
+        skip(self, article),
+        fields(article_id = article.id),
+        err
+    )]
+    pub async fn find_recipients(
+        &self,
+        article: &entity::articles::Model,
+    ) -> Result<Vec<String>, DbErr> {
+        let subscriptions = entity::comment_subscriptions::Entity::find()
+            .filter(entity::comment_subscriptions::Column::ArticleId.eq(article.id))
+            .order_by_asc(entity::comment_subscriptions::Column::Id)
+            .all(&self.database_connection)
+            .await?;
+
+        let mut recipients = Vec::new();
+        if let Some(author_email) = &article.author_email {
+            let unsubscribed = subscriptions.iter().any(|subscription| {
+                subscription.email == *author_email && subscription.unsubscribed_at.is_some()
+            });
+            if !unsubscribed {
+                recipients.push(author_email.clone());
+            }
+        }
+        for subscription in subscriptions {
+            if subscription.unsubscribed_at.is_none() && !recipients.contains(&subscription.email) {
+                recipients.push(subscription.email);
+            }
+        }
+
+        Ok(recipients)
+    }
+
+    /// Subscribes `email` to the comments on an article, again if it had
+    /// unsubscribed.
+    #[tracing::instrument(
+        name = "CommentSubscriptionsRepository::subscribe",
+        level = "debug",
+        skip(self),
+        err
+    )]
+    pub async fn subscribe(
+        &self,
+        article_id: i32,
+        email: &str,
+    ) -> Result<entity::comment_subscriptions::Model, DbErr> {
+        self.set_unsubscribed_at(article_id, email, None).await
+    }
+
+    /// Stops notifications to `email` about an article, including those it
+    /// gets as the article's author.
+    #[tracing::instrument(
+        name = "CommentSubscriptionsRepository::unsubscribe",
+        level = "debug",
+        skip(self),
+        err
+    )]
+    pub async fn unsubscribe(
+        &self,
+        article_id: i32,
+        email: &str,
+    ) -> Result<entity::comment_subscriptions::Model, DbErr> {
+        self.set_unsubscribed_at(article_id, email, Some(Utc::now()))
+            .await
+    }
+
+    async fn set_unsubscribed_at(
+        &self,
+        article_id: i32,
+        email: &str,
+        unsubscribed_at: Option<DateTime<Utc>>,
+    ) -> Result<entity::comment_subscriptions::Model, DbErr> {
+        match self.find_by_article_id_and_email(article_id, email).await? {
+            Some(subscription) => {
+                let mut subscription: entity::comment_subscriptions::ActiveModel =
+                    subscription.into();
+
+                subscription.unsubscribed_at = Set(unsubscribed_at);
+                subscription.updated_at = Set(Utc::now());
+
+                subscription.update(&self.database_connection).await
+            }
+            None => {
+                entity::comment_subscriptions::ActiveModel {
+                    article_id: Set(article_id),
+                    email: Set(email.to_owned()),
+                    unsubscribed_at: Set(unsubscribed_at),
+                    created_at: Set(Utc::now()),
+                    updated_at: Set(Utc::now()),
+                    ..Default::default()
+                }
+                .insert(&self.database_connection)
+                .await
+            }
+        }
+    }
+}
+
+pub struct BlogSettingsRepository {
+    pub database_connection: DatabaseConnection,
+}
+
+impl BlogSettingsRepository {
+    /// The blog keeps a single row of settings.
+    const ID: i32 = 1;
+
+    pub fn new(database_connection: DatabaseConnection) -> Self {
+        Self {
+            database_connection,
+        }
+    }
+
+    #[tracing::instrument(
+        name = "BlogSettingsRepository::find",
+        level = "debug",
+        skip(self),
+        err
+    )]
+    pub async fn find(&self) -> Result<entity::blog_settings::Model, DbErr> {
+        let blog_settings = entity::blog_settings::Entity::find_by_id(Self::ID)
+            .one(&self.database_connection)
+            .await?;
+
+        Ok(blog_settings.unwrap_or(entity::blog_settings::Model {
+            id: Self::ID,
+            comment_auto_approve: false,
+        }))
+    }
+
+    #[tracing::instrument(
+        name = "BlogSettingsRepository::update",
+        level = "debug",
+        skip(self, form_data),
+        err
+    )]
+    pub async fn update(
+        &self,
+        form_data: entity::blog_settings::Model,
+    ) -> Result<entity::blog_settings::Model, DbErr> {
+        let blog_settings = entity::blog_settings::Entity::find_by_id(Self::ID)
+            .one(&self.database_connection)
+            .await?;
+
+        let blog_settings = match blog_settings {
+            Some(blog_settings) => {
+                let mut blog_settings: entity::blog_settings::ActiveModel = blog_settings.into();
+                blog_settings.comment_auto_approve = Set(form_data.comment_auto_approve);
+                blog_settings.update(&self.database_connection).await?
+            }
+            None => {
+                entity::blog_settings::ActiveModel {
+                    id: Set(Self::ID),
+                    comment_auto_approve: Set(form_data.comment_auto_approve),
+                }
+                .insert(&self.database_connection)
+                .await?
+            }
+        };
+
+        Ok(blog_settings)
+    }
+}
+
+pub struct AttachmentsRepository {
+    pub database_connection: DatabaseConnection,
+}
+
+impl AttachmentsRepository {
+    pub fn new(database_connection: DatabaseConnection) -> Self {
+        Self {
+            database_connection,
+        }
+    }
+
+    #[tracing::instrument(
+        name = "AttachmentsRepository::find_by_id",
+        level = "debug",
+        skip(self),
+        err
+    )]
+    pub async fn find_by_id(&self, id: i32) -> Result<Option<entity::attachments::Model>, DbErr> {
+        let attachment = entity::attachments::Entity::find_by_id(id)
+            .one(&self.database_connection)
+            .await?;
+
+        Ok(attachment)
+    }
+
+    #[tracing::instrument(
+        name = "AttachmentsRepository::find_by_article_id",
+        level = "debug",
+        skip(self),
+        err
+    )]
+    pub async fn find_by_article_id(
+        &self,
+        article_id: i32,
+    ) -> Result<Vec<entity::attachments::Model>, DbErr> {
+        let attachments = entity::attachments::Entity::find()
+            .filter(entity::attachments::Column::ArticleId.eq(article_id))
+            .order_by_asc(entity::attachments::Column::Id)
+            .all(&self.database_connection)
+            .await?;
+
+        Ok(attachments)
+    }
+
+    #[tracing::instrument(
+        name = "AttachmentsRepository::find_by_article_id_and_id",
+        level = "debug",
+        skip(self),
+        err
+    )]
+    pub async fn find_by_article_id_and_id(
+        &self,
+        article_id: i32,
+        id: i32,
+    ) -> Result<Option<entity::attachments::Model>, DbErr> {
+        let attachment = entity::attachments::Entity::find_by_id(id)
+            .filter(entity::attachments::Column::ArticleId.eq(article_id))
+            .one(&self.database_connection)
+            .await?;
+
+        Ok(attachment)
+    }
+
+    /// Inserts all of `forms` or, should any insert fail, none of them.
+    #[tracing::instrument(
+        name = "AttachmentsRepository::create_many",
+        level = "debug",
+        skip(self, forms),
+        fields(count = forms.len()),
+        err
+    )]
+    pub async fn create_many(
+        &self,
+        forms: Vec<entity::attachments::Model>,
+    ) -> Result<Vec<entity::attachments::Model>, DbErr> {
+        let transaction = self.database_connection.begin().await?;
+
+        let mut created = Vec::with_capacity(forms.len());
+        for form_data in forms {
+            created.push(
+                entity::attachments::ActiveModel {
+                    article_id: Set(form_data.article_id),
+                    storage_key: Set(form_data.storage_key),
+                    file_name: Set(form_data.file_name),
+                    content_type: Set(form_data.content_type),
+                    size: Set(form_data.size),
+                    created_at: Set(Utc::now()),
+                    width: Set(form_data.width),
+                    height: Set(form_data.height),
+                    ..Default::default()
+                }
+                .insert(&transaction)
+                .await?,
+            );
+        }
+
+        transaction.commit().await?;
+
+        Ok(created)
+    }
+
+    #[tracing::instrument(
+        name = "AttachmentsRepository::delete",
+        level = "debug",
+        skip(self),
+        err
+    )]
+    pub async fn delete(&self, id: i32) -> Result<sea_orm::DeleteResult, DbErr> {
+        entity::attachments::Entity::delete_by_id(id)
+            .exec(&self.database_connection)
+            .await
+    }
+}
+
+pub struct AttachmentVariantsRepository {
+    pub database_connection: DatabaseConnection,
+}
+
+impl AttachmentVariantsRepository {
+    pub fn new(database_connection: DatabaseConnection) -> Self {
+        Self {
+            database_connection,
+        }
+    }
+
+    #[tracing::instrument(
+        name = "AttachmentVariantsRepository::find_by_attachment_id",
+        level = "debug",
+        skip(self),
+        err
+    )]
+    pub async fn find_by_attachment_id(
+        &self,
+        attachment_id: i32,
+    ) -> Result<Vec<entity::attachment_variants::Model>, DbErr> {
+        self.find_by_attachment_ids(vec![attachment_id]).await
+    }
+
+    /// Variants of all of `attachment_ids`, narrowest first.
+    #[tracing::instrument(
+        name = "AttachmentVariantsRepository::find_by_attachment_ids",
+        level = "debug",
+        skip(self),
+        err
+    )]
+    pub async fn find_by_attachment_ids(
+        &self,
+        attachment_ids: Vec<i32>,
+    ) -> Result<Vec<entity::attachment_variants::Model>, DbErr> {
+        if attachment_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let variants = entity::attachment_variants::Entity::find()
+            .filter(entity::attachment_variants::Column::AttachmentId.is_in(attachment_ids))
+            .order_by_asc(entity::attachment_variants::Column::Width)
+            .order_by_asc(entity::attachment_variants::Column::ContentType)
+            .all(&self.database_connection)
+            .await?;
+
+        Ok(variants)
+    }
+
+    #[tracing::instrument(
+        name = "AttachmentVariantsRepository::find_by_attachment_id_and_id",
+        level = "debug",
+        skip(self),
+        err
+    )]
+    pub async fn find_by_attachment_id_and_id(
+        &self,
+        attachment_id: i32,
+        id: i32,
+    ) -> Result<Option<entity::attachment_variants::Model>, DbErr> {
+        let variant = entity::attachment_variants::Entity::find_by_id(id)
+            .filter(entity::attachment_variants::Column::AttachmentId.eq(attachment_id))
+            .one(&self.database_connection)
+            .await?;
+
+        Ok(variant)
+    }
+
+    /// Swaps the variants of attachment `attachment_id` for `variants`, so
+    /// that generating them again leaves a single set behind.
+    #[tracing::instrument(
+        name = "AttachmentVariantsRepository::replace",
+        level = "debug",
+        skip(self, variants),
+        err
+    )]
+    pub async fn replace(
+        &self,
+        attachment_id: i32,
+        variants: Vec<entity::attachment_variants::Model>,
+    ) -> Result<Vec<entity::attachment_variants::Model>, DbErr> {
+        let transaction = self.database_connection.begin().await?;
+
+        entity::attachment_variants::Entity::delete_many()
+            .filter(entity::attachment_variants::Column::AttachmentId.eq(attachment_id))
+            .exec(&transaction)
+            .await?;
+
+        let mut created = Vec::with_capacity(variants.len());
+        for variant in variants {
+            created.push(
+                entity::attachment_variants::ActiveModel {
+                    attachment_id: Set(attachment_id),
+                    storage_key: Set(variant.storage_key),
+                    content_type: Set(variant.content_type),
+                    width: Set(variant.width),
+                    height: Set(variant.height),
+                    size: Set(variant.size),
+                    created_at: Set(Utc::now()),
+                    ..Default::default()
+                }
+                .insert(&transaction)
+                .await?,
+            );
+        }
+
+        transaction.commit().await?;
+
+        Ok(created)
+    }
+}
+
+pub struct WebhooksRepository {
+    pub database_connection: DatabaseConnection,
+}
+
+impl WebhooksRepository {
+    pub fn new(database_connection: DatabaseConnection) -> Self {
+        Self {
+            database_connection,
+        }
+    }
+
+    #[tracing::instrument(
+        name = "WebhooksRepository::find_all",
+        level = "debug",
+        skip(self),
+        err
+    )]
+    pub async fn find_all(&self) -> Result<Vec<entity::webhooks::Model>, DbErr> {
+        let webhooks = entity::webhooks::Entity::find()
+            .order_by_asc(entity::webhooks::Column::Id)
+            .all(&self.database_connection)
+            .await?;
+
+        Ok(webhooks)
+    }
+
+    #[tracing::instrument(
+        name = "WebhooksRepository::find_active",
+        level = "debug",
+        skip(self),
+        err
+    )]
+    pub async fn find_active(&self) -> Result<Vec<entity::webhooks::Model>, DbErr> {
+        let webhooks = entity::webhooks::Entity::find()
+            .filter(entity::webhooks::Column::Active.eq(true))
+            .order_by_asc(entity::webhooks::Column::Id)
+            .all(&self.database_connection)
+            .await?;
+
+        Ok(webhooks)
+    }
+
+    #[tracing::instrument(
+        name = "WebhooksRepository::find_by_id",
+        level = "debug",
+        skip(self),
+        err
+    )]
+    pub async fn find_by_id(&self, id: i32) -> Result<Option<entity::webhooks::Model>, DbErr> {
+        let webhook = entity::webhooks::Entity::find_by_id(id)
+            .one(&self.database_connection)
+            .await?;
+
+        Ok(webhook)
+    }
+
+    #[tracing::instrument(
+        name = "WebhooksRepository::create",
+        level = "debug",
+        skip(self, form_data),
+        err
+    )]
+    pub async fn create(
+        &self,
+        form_data: entity::webhooks::Model,
+    ) -> Result<entity::webhooks::Model, DbErr> {
+        let webhook = entity::webhooks::ActiveModel {
+            url: Set(form_data.url),
+            secret: Set(form_data.secret),
+            events: Set(form_data.events),
+            active: Set(form_data.active),
+            created_at: Set(Utc::now()),
+            updated_at: Set(Utc::now()),
+            ..Default::default()
+        }
+        .insert(&self.database_connection)
+        .await?;
+
+        Ok(webhook)
+    }
+
+    #[tracing::instrument(
+        name = "WebhooksRepository::update",
+        level = "debug",
+        skip(self, form_data),
+        fields(id = form_data.id),
+        err
+    )]
+    pub async fn update(
+        &self,
+        form_data: entity::webhooks::Model,
+    ) -> Result<entity::webhooks::Model, DbErr> {
+        let webhook = entity::webhooks::Entity::find_by_id(form_data.id)
+            .one(&self.database_connection)
+            .await?;
+
+        let mut webhook: entity::webhooks::ActiveModel = webhook.unwrap().into();
+
+        webhook.url = Set(form_data.url);
+        webhook.secret = Set(form_data.secret);
+        webhook.events = Set(form_data.events);
+        webhook.active = Set(form_data.active);
+        webhook.updated_at = Set(Utc::now());
+
+        webhook.update(&self.database_connection).await
+    }
+
+    #[tracing::instrument(name = "WebhooksRepository::delete", level = "debug", skip(self), err)]
+    pub async fn delete(&self, id: i32) -> Result<sea_orm::DeleteResult, DbErr> {
+        entity::webhooks::Entity::delete_by_id(id)
+            .exec(&self.database_connection)
+            .await
+    }
+}
+
+pub struct WebhookDeliveriesRepository {
+    pub database_connection: DatabaseConnection,
+}
+
+impl WebhookDeliveriesRepository {
+    pub fn new(database_connection: DatabaseConnection) -> Self {
+        Self {
+            database_connection,
+        }
+    }
+
+    /// The latest `limit` deliveries to `webhook_id`, newest first.
+    #[tracing::instrument(
+        name = "WebhookDeliveriesRepository::find_latest_by_webhook_id",
+        level = "debug",
+        skip(self),
+        err
+    )]
+    pub async fn find_latest_by_webhook_id(
+        &self,
+        webhook_id: i32,
+        limit: u64,
+    ) -> Result<Vec<entity::webhook_deliveries::Model>, DbErr> {
+        let deliveries = entity::webhook_deliveries::Entity::find()
+            .filter(entity::webhook_deliveries::Column::WebhookId.eq(webhook_id))
+            .order_by_desc(entity::webhook_deliveries::Column::Id)
+            .limit(limit)
+            .all(&self.database_connection)
+            .await?;
+
+        Ok(deliveries)
+    }
+
+    #[tracing::instrument(
+        name = "WebhookDeliveriesRepository::find_by_webhook_id_and_id",
+        level = "debug",
+        skip(self),
+        err
+    )]
+    pub async fn find_by_webhook_id_and_id(
+        &self,
+        webhook_id: i32,
+        id: i32,
+    ) -> Result<Option<entity::webhook_deliveries::Model>, DbErr> {
+        let delivery = entity::webhook_deliveries::Entity::find_by_id(id)
+            .filter(entity::webhook_deliveries::Column::WebhookId.eq(webhook_id))
+            .one(&self.database_connection)
+            .await?;
+
+        Ok(delivery)
+    }
+
+    #[tracing::instrument(
+        name = "WebhookDeliveriesRepository::find_attempts",
+        level = "debug",
+        skip(self),
+        err
+    )]
+    pub async fn find_attempts(
+        &self,
+        delivery_id: i32,
+    ) -> Result<Vec<entity::webhook_delivery_attempts::Model>, DbErr> {
+        let attempts = entity::webhook_delivery_attempts::Entity::find()
+            .filter(entity::webhook_delivery_attempts::Column::DeliveryId.eq(delivery_id))
+            .order_by_asc(entity::webhook_delivery_attempts::Column::Attempt)
+            .all(&self.database_connection)
+            .await?;
+
+        Ok(attempts)
+    }
+
+    #[tracing::instrument(
+        name = "WebhookDeliveriesRepository::find_by_id",
+        level = "debug",
+        skip(self),
+        err
+    )]
+    pub async fn find_by_id(
+        &self,
+        id: i32,
+    ) -> Result<Option<entity::webhook_deliveries::Model>, DbErr> {
+        let delivery = entity::webhook_deliveries::Entity::find_by_id(id)
+            .one(&self.database_connection)
+            .await?;
+
+        Ok(delivery)
+    }
+
+    /// Queues `payload` for each of `webhook_ids`, along with the jobs that
+    /// deliver them.
+    #[tracing::instrument(
+        name = "WebhookDeliveriesRepository::create_many",
+        level = "debug",
+        skip(self, payload),
+        err
+    )]
+    pub async fn create_many(
+        &self,
+        webhook_ids: Vec<i32>,
+        event: &str,
+        payload: &str,
+        max_attempts: i32,
+    ) -> Result<(), DbErr> {
+        let transaction = self.database_connection.begin().await?;
+
+        for webhook_id in webhook_ids {
+            create_delivery(&transaction, webhook_id, event, payload, max_attempts).await?;
+        }
+
+        transaction.commit().await
+    }
+
+    /// Queues a fresh copy of `delivery`, leaving the original and its
+    /// attempts untouched.
+    #[tracing::instrument(
+        name = "WebhookDeliveriesRepository::redeliver",
+        level = "debug",
+        skip(self, delivery),
+        fields(id = delivery.id),
+        err
+    )]
+    pub async fn redeliver(
+        &self,
+        delivery: &entity::webhook_deliveries::Model,
+        max_attempts: i32,
+    ) -> Result<entity::webhook_deliveries::Model, DbErr> {
+        let transaction = self.database_connection.begin().await?;
+
+        let delivery = create_delivery(
+            &transaction,
+            delivery.webhook_id,
+            &delivery.event,
+            &delivery.payload,
+            max_attempts,
+        )
+        .await?;
+
+        transaction.commit().await?;
+
+        Ok(delivery)
+    }
+
+    /// Stores the outcome of one attempt at `delivery` and moves the delivery
+    /// to `status`. `next_attempt_at` is when a pending delivery is retried.
+    #[tracing::instrument(
+        name = "WebhookDeliveriesRepository::record_attempt",
+        level = "debug",
+        skip(self, delivery, attempt),
+        fields(id = delivery.id),
+        err
+    )]
+    pub async fn record_attempt(
+        &self,
+        delivery: entity::webhook_deliveries::Model,
+        attempt: entity::webhook_delivery_attempts::Model,
+        status: WebhookDeliveryStatus,
+        next_attempt_at: Option<DateTime<Utc>>,
+    ) -> Result<entity::webhook_deliveries::Model, DbErr> {
+        let transaction = self.database_connection.begin().await?;
+
+        entity::webhook_delivery_attempts::ActiveModel {
+            delivery_id: Set(delivery.id),
+            attempt: Set(attempt.attempt),
+            response_status: Set(attempt.response_status),
+            error: Set(attempt.error),
+            duration_ms: Set(attempt.duration_ms),
+            created_at: Set(Utc::now()),
+            ..Default::default()
+        }
+        .insert(&transaction)
+        .await?;
+
+        let mut delivery: entity::webhook_deliveries::ActiveModel = delivery.into();
+
+        delivery.status = Set(status);
+        delivery.attempts = Set(attempt.attempt);
+        delivery.next_attempt_at = Set(next_attempt_at);
+        delivery.updated_at = Set(Utc::now());
+
+        let delivery = delivery.update(&transaction).await?;
+
+        transaction.commit().await?;
+
+        Ok(delivery)
+    }
+}
+
+/// Inserts a pending delivery and the job that delivers it, as part of
+/// `transaction`.
+async fn create_delivery(
+    transaction: &DatabaseTransaction,
+    webhook_id: i32,
+    event: &str,
+    payload: &str,
+    max_attempts: i32,
+) -> Result<entity::webhook_deliveries::Model, DbErr> {
+    let now = Utc::now();
+
+    let delivery = entity::webhook_deliveries::ActiveModel {
+        webhook_id: Set(webhook_id),
+        event: Set(event.to_owned()),
+        payload: Set(payload.to_owned()),
+        status: Set(WebhookDeliveryStatus::Pending),
+        attempts: Set(0),
+        next_attempt_at: Set(Some(now)),
+        created_at: Set(now),
+        updated_at: Set(now),
+        ..Default::default()
+    }
+    .insert(transaction)
+    .await?;
+
+    let job = jobs::Job::DeliverWebhook {
+        delivery_id: delivery.id,
+    };
+    job_active_model(&job, max_attempts, now)?
+        .insert(transaction)
+        .await?;
+
+    Ok(delivery)
+}
+
+fn job_active_model(
+    job: &jobs::Job,
+    max_attempts: i32,
+    run_at: DateTime<Utc>,
+) -> Result<entity::jobs::ActiveModel, DbErr> {
+    let payload = serde_json::to_string(job).map_err(|err| DbErr::Custom(err.to_string()))?;
+
+    Ok(entity::jobs::ActiveModel {
+        kind: Set(job.kind().to_owned()),
+        payload: Set(payload),
+        status: Set(JobStatus::Pending),
+        attempts: Set(0),
+        max_attempts: Set(max_attempts),
+        run_at: Set(run_at),
+        locked_at: Set(None),
+        last_error: Set(None),
+        created_at: Set(Utc::now()),
+        updated_at: Set(Utc::now()),
+        ..Default::default()
+    })
+}
+
+pub struct JobsRepository {
+    pub database_connection: DatabaseConnection,
+}
+
+impl JobsRepository {
+    pub fn new(database_connection: DatabaseConnection) -> Self {
+        Self {
+            database_connection,
+        }
+    }
+
+    /// The latest `limit` jobs, newest first, optionally only those in
+    /// `status` or of `kind`.
+    #[tracing::instrument(name = "JobsRepository::find_latest", level = "debug", skip(self), err)]
+    pub async fn find_latest(
+        &self,
+        status: Option<JobStatus>,
+        kind: Option<String>,
+        limit: u64,
+    ) -> Result<Vec<entity::jobs::Model>, DbErr> {
+        let mut select = entity::jobs::Entity::find();
+        if let Some(status) = status {
+            select = select.filter(entity::jobs::Column::Status.eq(status));
+        }
+        if let Some(kind) = kind {
+            select = select.filter(entity::jobs::Column::Kind.eq(kind));
+        }
+
+        let jobs = select
+            .order_by_desc(entity::jobs::Column::Id)
+            .limit(limit)
+            .all(&self.database_connection)
+            .await?;
+
+        Ok(jobs)
+    }
+
+    #[tracing::instrument(name = "JobsRepository::find_by_id", level = "debug", skip(self), err)]
+    pub async fn find_by_id(&self, id: i32) -> Result<Option<entity::jobs::Model>, DbErr> {
+        let job = entity::jobs::Entity::find_by_id(id)
+            .one(&self.database_connection)
+            .await?;
+
+        Ok(job)
+    }
+
+    #[tracing::instrument(name = "JobsRepository::create", level = "debug", skip(self), err)]
+    pub async fn create(
+        &self,
+        job: &jobs::Job,
+        max_attempts: i32,
+        run_at: DateTime<Utc>,
+    ) -> Result<entity::jobs::Model, DbErr> {
+        job_active_model(job, max_attempts, run_at)?
+            .insert(&self.database_connection)
+            .await
+    }
+
+    /// Inserts all of `jobs` or, should any insert fail, none of them.
+    #[tracing::instrument(
+        name = "JobsRepository::create_many",
+        level = "debug",
+        skip(self, jobs),
+        fields(count = jobs.len()),
+        err
+    )]
+    pub async fn create_many(
+        &self,
+        jobs: &[jobs::Job],
+        max_attempts: i32,
+        run_at: DateTime<Utc>,
+    ) -> Result<Vec<entity::jobs::Model>, DbErr> {
+        let transaction = self.database_connection.begin().await?;
+
+        let mut created = Vec::with_capacity(jobs.len());
+        for job in jobs {
+            created.push(
+                job_active_model(job, max_attempts, run_at)?
+                    .insert(&transaction)
+                    .await?,
+            );
+        }
+
+        transaction.commit().await?;
+
+        Ok(created)
+    }
+
+    /// Locks the next due job and marks it as running, skipping jobs other
+    /// workers hold. Running jobs locked before `lease_expired_before` are
+    /// taken over, as their worker is presumed gone.
+    #[tracing::instrument(name = "JobsRepository::claim_next", level = "debug", skip(self), err)]
+    pub async fn claim_next(
+        &self,
+        now: DateTime<Utc>,
+        lease_expired_before: DateTime<Utc>,
+    ) -> Result<Option<entity::jobs::Model>, DbErr> {
+        let transaction = self.database_connection.begin().await?;
+
+        let mut select = entity::jobs::Entity::find()
+            .filter(
+                Condition::any()
+                    .add(
+                        Condition::all()
+                            .add(entity::jobs::Column::Status.eq(JobStatus::Pending))
+                            .add(entity::jobs::Column::RunAt.lte(now)),
+                    )
+                    .add(
+                        Condition::all()
+                            .add(entity::jobs::Column::Status.eq(JobStatus::Running))
+                            .add(entity::jobs::Column::LockedAt.lt(lease_expired_before)),
+                    ),
+            )
+            .order_by_asc(entity::jobs::Column::RunAt)
+            .order_by_asc(entity::jobs::Column::Id)
+            .limit(1);
+        QuerySelect::query(&mut select)
+            .lock_with_behavior(LockType::Update, LockBehavior::SkipLocked);
+
+        let job = match select.one(&transaction).await? {
+            Some(job) => job,
+            None => return Ok(None),
+        };
+
+        let attempts = job.attempts + 1;
+        let mut job: entity::jobs::ActiveModel = job.into();
+
+        job.status = Set(JobStatus::Running);
+        job.attempts = Set(attempts);
+        job.locked_at = Set(Some(now));
+        job.updated_at = Set(Utc::now());
+
+        let job = job.update(&transaction).await?;
+
+        transaction.commit().await?;
+
+        Ok(Some(job))
+    }
+
+    /// Records a successful run of job `id`, its `attempts`th. Returns whether
+    /// that run still held the job: it does not once its lease expired and
+    /// another worker claimed the job, which then leaves it alone.
+    #[tracing::instrument(name = "JobsRepository::complete", level = "debug", skip(self), err)]
+    pub async fn complete(&self, id: i32, attempts: i32) -> Result<bool, DbErr> {
+        let result = entity::jobs::Entity::update_many()
+            .col_expr(
+                entity::jobs::Column::Status,
+                Expr::value(JobStatus::Succeeded),
+            )
+            .col_expr(
+                entity::jobs::Column::LockedAt,
+                Expr::value(Option::<DateTime<Utc>>::None),
+            )
+            .col_expr(entity::jobs::Column::UpdatedAt, Expr::value(Utc::now()))
+            .filter(entity::jobs::Column::Id.eq(id))
+            .filter(entity::jobs::Column::Status.eq(JobStatus::Running))
+            .filter(entity::jobs::Column::Attempts.eq(attempts))
+            .exec(&self.database_connection)
+            .await?;
+
+        Ok(result.rows_affected > 0)
+    }
+
+    /// Records a failed run of job `id`, its `attempts`th. It runs again at
+    /// `retry_at`, or moves to the dead letter queue when that is `None`.
+    /// Returns whether that run still held the job, as for
+    /// [`JobsRepository::complete`].
+    #[tracing::instrument(name = "JobsRepository::fail", level = "debug", skip(self, error), err)]
+    pub async fn fail(
+        &self,
+        id: i32,
+        attempts: i32,
+        error: String,
+        retry_at: Option<DateTime<Utc>>,
+    ) -> Result<bool, DbErr> {
+        let mut update = entity::jobs::Entity::update_many()
+            .col_expr(
+                entity::jobs::Column::LockedAt,
+                Expr::value(Option::<DateTime<Utc>>::None),
+            )
+            .col_expr(entity::jobs::Column::LastError, Expr::value(error))
+            .col_expr(entity::jobs::Column::UpdatedAt, Expr::value(Utc::now()));
+
+        update = match retry_at {
+            Some(retry_at) => update
+                .col_expr(
+                    entity::jobs::Column::Status,
+                    Expr::value(JobStatus::Pending),
+                )
+                .col_expr(entity::jobs::Column::RunAt, Expr::value(retry_at)),
+            None => update.col_expr(entity::jobs::Column::Status, Expr::value(JobStatus::Dead)),
+        };
+
+        let result = update
+            .filter(entity::jobs::Column::Id.eq(id))
+            .filter(entity::jobs::Column::Status.eq(JobStatus::Running))
+            .filter(entity::jobs::Column::Attempts.eq(attempts))
+            .exec(&self.database_connection)
+            .await?;
+
+        Ok(result.rows_affected > 0)
+    }
+
+    /// Makes job `id` due now with a fresh set of attempts.
+    #[tracing::instrument(name = "JobsRepository::retry", level = "debug", skip(self), err)]
+    pub async fn retry(&self, id: i32) -> Result<entity::jobs::Model, DbErr> {
+        let job = entity::jobs::Entity::find_by_id(id)
+            .one(&self.database_connection)
+            .await?;
+
+        let mut job: entity::jobs::ActiveModel = job.unwrap().into();
+
+        job.status = Set(JobStatus::Pending);
+        job.attempts = Set(0);
+        job.run_at = Set(Utc::now());
+        job.locked_at = Set(None);
+        job.updated_at = Set(Utc::now());
+
+        job.update(&self.database_connection).await
+    }
+}
@@ -0,0 +1,76 @@
+use actix_web::web::Bytes;
+use async_stream::try_stream;
+use futures_util::{Stream, TryStreamExt};
+use sea_orm::{DatabaseConnection, DbErr};
+
+use crate::{page, repository};
+
+pub const CONTENT_TYPE: &str = "application/xml; charset=utf-8";
+
+/// The sitemap protocol caps a single sitemap at 50,000 URLs. Past that the
+/// URLs are split across several sitemaps listed by a sitemap index.
+pub const MAX_URLS: u64 = 50_000;
+
+const XML_DECLARATION: &str = r#"<?xml version="1.0" encoding="UTF-8"?>"#;
+const XMLNS: &str = "http://www.sitemaps.org/schemas/sitemap/0.9";
+
+fn escape_xml(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+pub fn page_count(article_count: u64) -> u64 {
+    article_count.div_ceil(MAX_URLS).max(1)
+}
+
+pub fn index(base_url: &str, page_count: u64) -> String {
+    let mut xml = format!("{XML_DECLARATION}\n<sitemapindex xmlns=\"{XMLNS}\">\n");
+    for page in 1..=page_count {
+        xml.push_str(&format!(
+            "<sitemap><loc>{}</loc></sitemap>\n",
+            escape_xml(&format!("{base_url}/sitemaps/{page}.xml"))
+        ));
+    }
+    xml.push_str("</sitemapindex>\n");
+    xml
+}
+
+/// Streams the `urlset` of the given 1-based page, writing each article as it
+/// is read from the database.
+pub fn urlset(
+    database_connection: DatabaseConnection,
+    base_url: String,
+    page: u64,
+) -> impl Stream<Item = Result<Bytes, DbErr>> {
+    try_stream! {
+        let articles_repository = repository::ArticlesRepository::new(database_connection);
+
+        yield Bytes::from(format!("{XML_DECLARATION}\n<urlset xmlns=\"{XMLNS}\">\n"));
+
+        let mut entries = Box::pin(
+            articles_repository
+                .stream_sitemap_entries((page - 1) * MAX_URLS, MAX_URLS)
+                .await?,
+        );
+
+        while let Some(entry) = entries.try_next().await? {
+            yield Bytes::from(format!(
+                "<url><loc>{}</loc><lastmod>{}</lastmod></url>\n",
+                escape_xml(&format!("{}{}", base_url, page::article_path(entry.id))),
+                entry.updated_at.format("%Y-%m-%d")
+            ));
+        }
+
+        yield Bytes::from_static(b"</urlset>\n");
+    }
+}
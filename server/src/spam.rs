@@ -0,0 +1,436 @@
+use std::{
+    collections::{HashMap, HashSet},
+    env, fmt,
+    sync::{Arc, RwLock},
+};
+
+use async_trait::async_trait;
+use chrono::{Duration, Utc};
+use sea_orm::{DatabaseConnection, DbErr};
+
+use crate::repository;
+use entity::sea_orm_active_enums::ModerationStatus;
+
+/// A comment that is about to be stored.
+pub struct CommentCandidate<'a> {
+    pub article_id: i32,
+    pub body: &'a str,
+}
+
+/// One step of the spam pipeline. Each filter scores a comment independently and
+/// the pipeline adds the scores up, so a score of 1.0 from a single filter is
+/// enough on its own to reach the default threshold.
+#[async_trait]
+pub trait SpamFilter: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    async fn score(&self, candidate: &CommentCandidate<'_>) -> Result<f64, DbErr>;
+}
+
+fn words(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| word.chars().count() >= 2)
+        .map(|word| word.to_lowercase())
+}
+
+fn links(text: &str) -> impl Iterator<Item = &str> {
+    text.split_whitespace().filter(|token| {
+        let token = token.to_ascii_lowercase();
+        token.contains("http://") || token.contains("https://") || token.contains("www.")
+    })
+}
+
+fn link_host(link: &str) -> Option<String> {
+    let start = link
+        .find("://")
+        .map(|index| index + 3)
+        .or_else(|| link.to_ascii_lowercase().find("www."))?;
+    let host = link[start..]
+        .split(['/', '?', '#', ':', ')', ']', '"', '\'', '>'])
+        .next()?
+        .trim_end_matches('.')
+        .to_ascii_lowercase();
+
+    if host.is_empty() {
+        None
+    } else {
+        Some(host)
+    }
+}
+
+pub struct LinkCountFilter {
+    pub max_links: usize,
+}
+
+#[async_trait]
+impl SpamFilter for LinkCountFilter {
+    fn name(&self) -> &'static str {
+        "link_count"
+    }
+
+    async fn score(&self, candidate: &CommentCandidate<'_>) -> Result<f64, DbErr> {
+        let count = links(candidate.body).count();
+
+        Ok(count.saturating_sub(self.max_links) as f64 * 0.5)
+    }
+}
+
+pub struct BlocklistFilter {
+    pub words: HashSet<String>,
+    pub domains: Vec<String>,
+}
+
+#[async_trait]
+impl SpamFilter for BlocklistFilter {
+    fn name(&self) -> &'static str {
+        "blocklist"
+    }
+
+    async fn score(&self, candidate: &CommentCandidate<'_>) -> Result<f64, DbErr> {
+        let blocked_words = words(candidate.body)
+            .filter(|word| self.words.contains(word))
+            .count();
+
+        let blocked_links = links(candidate.body)
+            .filter_map(link_host)
+            .filter(|host| {
+                self.domains
+                    .iter()
+                    .any(|domain| host == domain || host.ends_with(&format!(".{domain}")))
+            })
+            .count();
+
+        Ok((blocked_words + blocked_links) as f64)
+    }
+}
+
+/// Scores a comment whose body was already submitted on the same article
+/// within `window`. Short replies such as "Thanks!" repeat innocently, so
+/// `score` should stay below the threshold and only add to other signals.
+pub struct RepeatedSubmissionFilter {
+    pub database_connection: DatabaseConnection,
+    pub window: Duration,
+    pub score: f64,
+}
+
+#[async_trait]
+impl SpamFilter for RepeatedSubmissionFilter {
+    fn name(&self) -> &'static str {
+        "repeated_submission"
+    }
+
+    async fn score(&self, candidate: &CommentCandidate<'_>) -> Result<f64, DbErr> {
+        let comments_repository =
+            repository::CommentsRepository::new(self.database_connection.clone());
+
+        let count = comments_repository
+            .count_by_article_id_and_body_since(
+                candidate.article_id,
+                candidate.body,
+                Utc::now() - self.window,
+            )
+            .await?;
+
+        Ok(if count > 0 { self.score } else { 0.0 })
+    }
+}
+
+/// A multinomial naive Bayes classifier over the words of a comment, trained
+/// from the comments moderators marked as spam against the approved ones.
+#[derive(Default)]
+pub struct NaiveBayes {
+    spam_documents: u64,
+    ham_documents: u64,
+    spam_words: HashMap<String, u64>,
+    ham_words: HashMap<String, u64>,
+    spam_word_total: u64,
+    ham_word_total: u64,
+}
+
+impl NaiveBayes {
+    fn counts(&mut self, spam: bool) -> (&mut u64, &mut HashMap<String, u64>, &mut u64) {
+        if spam {
+            (
+                &mut self.spam_documents,
+                &mut self.spam_words,
+                &mut self.spam_word_total,
+            )
+        } else {
+            (
+                &mut self.ham_documents,
+                &mut self.ham_words,
+                &mut self.ham_word_total,
+            )
+        }
+    }
+
+    pub fn train(&mut self, text: &str, spam: bool) {
+        let (documents, counts, total) = self.counts(spam);
+        *documents += 1;
+        for word in words(text) {
+            *counts.entry(word).or_default() += 1;
+            *total += 1;
+        }
+    }
+
+    pub fn untrain(&mut self, text: &str, spam: bool) {
+        let (documents, counts, total) = self.counts(spam);
+        *documents = documents.saturating_sub(1);
+        for word in words(text) {
+            if let Some(count) = counts.get_mut(&word) {
+                *count -= 1;
+                *total -= 1;
+                if *count == 0 {
+                    counts.remove(&word);
+                }
+            }
+        }
+    }
+
+    /// Probability that `text` is spam, or `None` until both classes have been
+    /// seen at least once.
+    pub fn spam_probability(&self, text: &str) -> Option<f64> {
+        if self.spam_documents == 0 || self.ham_documents == 0 {
+            return None;
+        }
+
+        let vocabulary = self
+            .spam_words
+            .keys()
+            .chain(self.ham_words.keys())
+            .collect::<HashSet<&String>>()
+            .len() as f64;
+        let documents = (self.spam_documents + self.ham_documents) as f64;
+
+        let mut spam = (self.spam_documents as f64 / documents).ln();
+        let mut ham = (self.ham_documents as f64 / documents).ln();
+        for word in words(text) {
+            let spam_count = self.spam_words.get(&word).copied().unwrap_or(0) as f64;
+            let ham_count = self.ham_words.get(&word).copied().unwrap_or(0) as f64;
+            spam += ((spam_count + 1.0) / (self.spam_word_total as f64 + vocabulary)).ln();
+            ham += ((ham_count + 1.0) / (self.ham_word_total as f64 + vocabulary)).ln();
+        }
+
+        Some(1.0 / (1.0 + (ham - spam).exp()))
+    }
+}
+
+pub struct NaiveBayesFilter {
+    pub classifier: Arc<RwLock<NaiveBayes>>,
+}
+
+#[async_trait]
+impl SpamFilter for NaiveBayesFilter {
+    fn name(&self) -> &'static str {
+        "naive_bayes"
+    }
+
+    async fn score(&self, candidate: &CommentCandidate<'_>) -> Result<f64, DbErr> {
+        let classifier = self.classifier.read().unwrap();
+
+        Ok(classifier.spam_probability(candidate.body).unwrap_or(0.0))
+    }
+}
+
+pub struct SpamVerdict {
+    pub score: f64,
+    pub spam: bool,
+}
+
+pub struct SpamPipeline {
+    filters: Vec<Box<dyn SpamFilter>>,
+    classifier: Arc<RwLock<NaiveBayes>>,
+    threshold: f64,
+}
+
+impl fmt::Debug for SpamPipeline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SpamPipeline")
+            .field(
+                "filters",
+                &self
+                    .filters
+                    .iter()
+                    .map(|filter| filter.name())
+                    .collect::<Vec<_>>(),
+            )
+            .field("threshold", &self.threshold)
+            .finish()
+    }
+}
+
+fn env_list(key: &str) -> Vec<String> {
+    env::var(key)
+        .unwrap_or_default()
+        .split(',')
+        .map(|value| value.trim().to_lowercase())
+        .filter(|value| !value.is_empty())
+        .collect()
+}
+
+fn env_parse<T: std::str::FromStr>(key: &str, default: T) -> T {
+    env::var(key)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(default)
+}
+
+impl SpamPipeline {
+    pub fn new(threshold: f64) -> Self {
+        Self {
+            filters: vec![],
+            classifier: Arc::new(RwLock::new(NaiveBayes::default())),
+            threshold,
+        }
+    }
+
+    pub fn with_filter(mut self, filter: impl SpamFilter + 'static) -> Self {
+        self.filters.push(Box::new(filter));
+        self
+    }
+
+    /// Builds the default pipeline from `SPAM_*` environment variables and trains
+    /// its classifier from the comments moderators have already reviewed.
+    pub async fn from_env(database_connection: &DatabaseConnection) -> Result<Self, DbErr> {
+        let mut pipeline = Self::new(env_parse("SPAM_THRESHOLD", 1.0));
+        let classifier = pipeline.classifier.clone();
+
+        pipeline = pipeline
+            .with_filter(LinkCountFilter {
+                max_links: env_parse("SPAM_MAX_LINKS", 2),
+            })
+            .with_filter(BlocklistFilter {
+                words: env_list("SPAM_BLOCKED_WORDS").into_iter().collect(),
+                domains: env_list("SPAM_BLOCKED_DOMAINS"),
+            })
+            .with_filter(RepeatedSubmissionFilter {
+                database_connection: database_connection.clone(),
+                window: Duration::seconds(env_parse("SPAM_REPEAT_WINDOW_SECONDS", 3600)),
+                score: env_parse("SPAM_REPEAT_SCORE", 0.5),
+            })
+            .with_filter(NaiveBayesFilter { classifier });
+
+        pipeline.train(database_connection).await?;
+
+        Ok(pipeline)
+    }
+
+    async fn train(&self, database_connection: &DatabaseConnection) -> Result<(), DbErr> {
+        let comments_repository = repository::CommentsRepository::new(database_connection.clone());
+
+        let spam = comments_repository
+            .find_all_moderated_by_moderation_status(ModerationStatus::Spam)
+            .await?;
+        let ham = comments_repository
+            .find_all_moderated_by_moderation_status(ModerationStatus::Approved)
+            .await?;
+
+        let mut classifier = self.classifier.write().unwrap();
+        for comment in spam.iter() {
+            classifier.train(&comment.body, true);
+        }
+        for comment in ham.iter() {
+            classifier.train(&comment.body, false);
+        }
+
+        Ok(())
+    }
+
+    pub async fn evaluate(&self, candidate: &CommentCandidate<'_>) -> Result<SpamVerdict, DbErr> {
+        let mut score = 0.0;
+        for filter in self.filters.iter() {
+            score += filter.score(candidate).await?;
+        }
+
+        Ok(SpamVerdict {
+            score,
+            spam: score >= self.threshold,
+        })
+    }
+
+    /// Keeps the classifier in step with a moderation decision moving a
+    /// comment to `current`. `previous` is the status a moderator gave it
+    /// before, `None` if none did: the classifier only learns from moderators,
+    /// never from its own verdicts or from auto-approval.
+    pub fn learn(&self, body: &str, previous: Option<ModerationStatus>, current: ModerationStatus) {
+        let label = |status| match status {
+            Some(ModerationStatus::Spam) => Some(true),
+            Some(ModerationStatus::Approved) => Some(false),
+            _ => None,
+        };
+
+        if label(previous) == label(Some(current)) {
+            return;
+        }
+
+        let mut classifier = self.classifier.write().unwrap();
+        if let Some(spam) = label(previous) {
+            classifier.untrain(body, spam);
+        }
+        if let Some(spam) = label(Some(current)) {
+            classifier.train(body, spam);
+        }
+    }
+
+    /// Untrains what a moderator's decision on `comment` taught the
+    /// classifier, for when its body is edited and the decision no longer
+    /// applies to it.
+    pub fn forget(&self, comment: &entity::comments::Model) {
+        if comment.moderated_at.is_none() {
+            return;
+        }
+
+        let mut classifier = self.classifier.write().unwrap();
+        match comment.moderation_status {
+            ModerationStatus::Spam => classifier.untrain(&comment.body, true),
+            ModerationStatus::Approved => classifier.untrain(&comment.body, false),
+            _ => {}
+        }
+    }
+
+    /// Status a comment moves to when its body is edited. The new body is
+    /// screened like a new comment, so that a visible comment cannot be edited
+    /// into spam unreviewed, but an edit never lifts a rejection or a spam
+    /// verdict.
+    pub async fn edited_moderation_status(
+        &self,
+        database_connection: &DatabaseConnection,
+        candidate: &CommentCandidate<'_>,
+        previous: ModerationStatus,
+    ) -> Result<ModerationStatus, DbErr> {
+        match previous {
+            ModerationStatus::Rejected | ModerationStatus::Spam => Ok(previous),
+            _ => {
+                self.initial_moderation_status(database_connection, candidate)
+                    .await
+            }
+        }
+    }
+
+    /// Status a new comment is stored with: spam is quarantined, everything else
+    /// follows the blog's auto-approval setting.
+    pub async fn initial_moderation_status(
+        &self,
+        database_connection: &DatabaseConnection,
+        candidate: &CommentCandidate<'_>,
+    ) -> Result<ModerationStatus, DbErr> {
+        let verdict = self.evaluate(candidate).await?;
+        if verdict.spam {
+            tracing::info!(
+                "quarantined comment on article {} as spam (score {:.2})",
+                candidate.article_id,
+                verdict.score
+            );
+            return Ok(ModerationStatus::Spam);
+        }
+
+        let blog_settings_repository =
+            repository::BlogSettingsRepository::new(database_connection.clone());
+
+        if blog_settings_repository.find().await?.comment_auto_approve {
+            Ok(ModerationStatus::Approved)
+        } else {
+            Ok(ModerationStatus::Pending)
+        }
+    }
+}
@@ -0,0 +1,163 @@
+use std::{
+    fmt, fs, io,
+    path::{Component, Path, PathBuf},
+};
+
+use actix_files::NamedFile;
+use actix_web::{
+    http::header::{Charset, ContentDisposition, DispositionParam, DispositionType, ExtendedValue},
+    web::{self, Bytes},
+    HttpRequest, HttpResponse,
+};
+use async_trait::async_trait;
+use mime::Mime;
+
+/// Where uploaded files live. Objects are addressed by keys such as
+/// `12/3f2a....png`, chosen by the caller and never derived from user input.
+/// Not `Send`, like everything else running on actix's per-thread runtimes.
+#[async_trait(?Send)]
+pub trait Storage: Send + Sync + fmt::Debug {
+    fn name(&self) -> &'static str;
+
+    /// Stores `data` under `key`, replacing any object already there.
+    async fn put(&self, key: &str, data: Bytes) -> io::Result<()>;
+
+    /// Reads the whole object under `key`.
+    async fn get(&self, key: &str) -> io::Result<Bytes>;
+
+    /// Removes the object under `key`. Removing a missing object succeeds.
+    async fn delete(&self, key: &str) -> io::Result<()>;
+
+    /// Removes every object whose key starts with `{prefix}/`.
+    async fn delete_prefix(&self, prefix: &str) -> io::Result<()>;
+
+    /// Answers `request` with the object under `key`, including conditional
+    /// and range requests.
+    async fn respond(
+        &self,
+        key: &str,
+        content_type: Mime,
+        content_disposition: ContentDisposition,
+        request: &HttpRequest,
+    ) -> io::Result<HttpResponse>;
+}
+
+/// Keeps objects as files below `root`.
+#[derive(Debug)]
+pub struct LocalStorage {
+    root: PathBuf,
+}
+
+impl LocalStorage {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// Resolves `key` below the root, refusing keys that would escape it.
+    fn path(&self, key: &str) -> io::Result<PathBuf> {
+        let key = Path::new(key);
+
+        if key.as_os_str().is_empty()
+            || !key
+                .components()
+                .all(|component| matches!(component, Component::Normal(_)))
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid storage key",
+            ));
+        }
+
+        Ok(self.root.join(key))
+    }
+}
+
+#[async_trait(?Send)]
+impl Storage for LocalStorage {
+    fn name(&self) -> &'static str {
+        "local"
+    }
+
+    async fn put(&self, key: &str, data: Bytes) -> io::Result<()> {
+        let path = self.path(key)?;
+
+        web::block(move || {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(path, data)
+        })
+        .await
+        .map_err(io::Error::other)?
+    }
+
+    async fn get(&self, key: &str) -> io::Result<Bytes> {
+        let path = self.path(key)?;
+
+        web::block(move || fs::read(path).map(Bytes::from))
+            .await
+            .map_err(io::Error::other)?
+    }
+
+    async fn delete(&self, key: &str) -> io::Result<()> {
+        let path = self.path(key)?;
+
+        match web::block(move || fs::remove_file(path))
+            .await
+            .map_err(io::Error::other)?
+        {
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+            result => result,
+        }
+    }
+
+    async fn delete_prefix(&self, prefix: &str) -> io::Result<()> {
+        let path = self.path(prefix)?;
+
+        match web::block(move || fs::remove_dir_all(path))
+            .await
+            .map_err(io::Error::other)?
+        {
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+            result => result,
+        }
+    }
+
+    async fn respond(
+        &self,
+        key: &str,
+        content_type: Mime,
+        content_disposition: ContentDisposition,
+        request: &HttpRequest,
+    ) -> io::Result<HttpResponse> {
+        let file = NamedFile::open_async(self.path(key)?).await?;
+
+        Ok(file
+            .set_content_type(content_type)
+            .set_content_disposition(content_disposition)
+            .into_response(request))
+    }
+}
+
+/// `inline` or `attachment` disposition carrying the original file name, as
+/// `filename*` when it is not plain ASCII.
+pub fn content_disposition(inline: bool, file_name: &str) -> ContentDisposition {
+    let parameter = if file_name.is_ascii() {
+        DispositionParam::Filename(file_name.to_string())
+    } else {
+        DispositionParam::FilenameExt(ExtendedValue {
+            charset: Charset::Ext("UTF-8".to_string()),
+            language_tag: None,
+            value: file_name.as_bytes().to_vec(),
+        })
+    };
+
+    ContentDisposition {
+        disposition: if inline {
+            DispositionType::Inline
+        } else {
+            DispositionType::Attachment
+        },
+        parameters: vec![parameter],
+    }
+}
@@ -0,0 +1,43 @@
+use std::{env, sync::Arc};
+
+use tracing::Level;
+use tracing_subscriber::{
+    filter::Targets, fmt::format::FmtSpan, layer::SubscriberExt, util::SubscriberInitExt,
+    EnvFilter, Layer,
+};
+
+use crate::metrics::{self, Metrics};
+
+/// Used when `RUST_LOG` is not set: our own spans down to repository calls,
+/// everything else from `info`.
+const DEFAULT_FILTER: &str = "info,server=debug";
+
+/// Installs the global `tracing` subscriber.
+///
+/// `LOG_FORMAT=json` writes one JSON object per line, including the fields of
+/// the enclosing spans, instead of human readable text. Closed spans are logged
+/// with their durations, and records from crates still on `log` are forwarded.
+///
+/// Repository spans also feed the query duration histogram in `metrics`. That
+/// layer has its own filter, so it keeps working whatever `RUST_LOG` says.
+pub fn init(metrics: Arc<Metrics>) {
+    let filter =
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(DEFAULT_FILTER));
+    let fmt_layer = tracing_subscriber::fmt::layer().with_span_events(FmtSpan::CLOSE);
+    let fmt_layer = match env::var("LOG_FORMAT").as_deref() {
+        Ok("json") => fmt_layer
+            .json()
+            .with_current_span(true)
+            .with_span_list(true)
+            .boxed(),
+        _ => fmt_layer.boxed(),
+    };
+
+    tracing_subscriber::registry()
+        .with(fmt_layer.with_filter(filter))
+        .with(
+            metrics::QueryDurationLayer::new(metrics)
+                .with_filter(Targets::new().with_target(metrics::REPOSITORY_TARGET, Level::DEBUG)),
+        )
+        .init();
+}
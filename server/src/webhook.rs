@@ -0,0 +1,365 @@
+use std::{
+    env,
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Utc};
+use entity::sea_orm_active_enums::{ModerationStatus, WebhookDeliveryStatus};
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sea_orm::DatabaseConnection;
+use serde::Serialize;
+use sha2::Sha256;
+
+use crate::{jobs, repository};
+
+pub const DEFAULT_MAX_ATTEMPTS: i32 = 8;
+
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub const SIGNATURE_HEADER: &str = "X-Webhook-Signature";
+pub const EVENT_HEADER: &str = "X-Webhook-Event";
+pub const DELIVERY_HEADER: &str = "X-Webhook-Delivery";
+
+/// Subscribes a webhook to every event.
+pub const ALL_EVENTS: &str = "*";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WebhookEvent {
+    ArticleCreated,
+    ArticleUpdated,
+    ArticleDeleted,
+    CommentCreated,
+    CommentUpdated,
+    CommentDeleted,
+}
+
+impl WebhookEvent {
+    pub const ALL: [Self; 6] = [
+        Self::ArticleCreated,
+        Self::ArticleUpdated,
+        Self::ArticleDeleted,
+        Self::CommentCreated,
+        Self::CommentUpdated,
+        Self::CommentDeleted,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::ArticleCreated => "article.created",
+            Self::ArticleUpdated => "article.updated",
+            Self::ArticleDeleted => "article.deleted",
+            Self::CommentCreated => "comment.created",
+            Self::CommentUpdated => "comment.updated",
+            Self::CommentDeleted => "comment.deleted",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|event| event.as_str() == value)
+    }
+}
+
+/// Whether a webhook storing `events`, a comma separated list of event names
+/// or [`ALL_EVENTS`], wants to receive `event`.
+pub fn subscribes_to(events: &str, event: WebhookEvent) -> bool {
+    events
+        .split(',')
+        .any(|value| value == ALL_EVENTS || value == event.as_str())
+}
+
+/// Random 32 byte secret, hex encoded.
+pub fn generate_secret() -> String {
+    let mut secret = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut secret);
+
+    hex::encode(secret)
+}
+
+/// Value of the [`SIGNATURE_HEADER`]: an HMAC-SHA256 of `{timestamp}.{body}`
+/// keyed with the webhook secret. Receivers should recompute it and reject
+/// stale timestamps, so that captured requests cannot be replayed.
+pub fn signature(secret: &str, timestamp: i64, body: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(format!("{timestamp}.{body}").as_bytes());
+
+    format!(
+        "t={timestamp},v1={}",
+        hex::encode(mac.finalize().into_bytes())
+    )
+}
+
+#[derive(Serialize)]
+struct Payload<T> {
+    event: &'static str,
+    occurred_at: DateTime<Utc>,
+    data: T,
+}
+
+#[derive(Serialize)]
+struct ArticlePayload {
+    id: i32,
+    title: String,
+    body: String,
+    category_id: i32,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+}
+
+impl From<&entity::articles::Model> for ArticlePayload {
+    fn from(article: &entity::articles::Model) -> Self {
+        Self {
+            id: article.id,
+            title: article.title.clone(),
+            body: article.body.clone(),
+            category_id: article.category_id,
+            created_at: article.created_at,
+            updated_at: article.updated_at,
+        }
+    }
+}
+
+/// Unlike live comment events, webhooks see every comment whatever its
+/// moderation status, so that receivers can act on comments held for review.
+#[derive(Serialize)]
+struct CommentPayload {
+    id: i32,
+    article_id: i32,
+    parent_id: Option<i32>,
+    body: String,
+    moderation_status: ModerationStatus,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+}
+
+impl From<&entity::comments::Model> for CommentPayload {
+    fn from(comment: &entity::comments::Model) -> Self {
+        Self {
+            id: comment.id,
+            article_id: comment.article_id,
+            parent_id: comment.parent_id,
+            body: comment.body.clone(),
+            moderation_status: comment.moderation_status,
+            created_at: comment.created_at,
+            updated_at: comment.updated_at,
+        }
+    }
+}
+
+/// Queues signed JSON payloads for the webhooks subscribed to each event, as
+/// one job per delivery. Failed attempts are retried by the job queue, with
+/// its exponential backoff.
+#[derive(Debug, Clone)]
+pub struct WebhookDispatcher {
+    database_connection: DatabaseConnection,
+    jobs: jobs::JobQueue,
+    client: reqwest::Client,
+    max_attempts: i32,
+}
+
+impl WebhookDispatcher {
+    pub fn new(
+        database_connection: DatabaseConnection,
+        jobs: jobs::JobQueue,
+        max_attempts: i32,
+        timeout: Duration,
+    ) -> Self {
+        Self {
+            database_connection,
+            jobs,
+            client: reqwest::Client::builder()
+                .timeout(timeout)
+                .build()
+                .expect("failed to build the webhook HTTP client"),
+            max_attempts: max_attempts.max(1),
+        }
+    }
+
+    /// Reads the number of attempts per delivery from `WEBHOOK_MAX_ATTEMPTS`,
+    /// and the timeout of each attempt from `WEBHOOK_TIMEOUT_SECONDS`.
+    pub fn from_env(database_connection: DatabaseConnection, jobs: jobs::JobQueue) -> Self {
+        let max_attempts = env::var("WEBHOOK_MAX_ATTEMPTS")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(DEFAULT_MAX_ATTEMPTS);
+        let timeout = env::var("WEBHOOK_TIMEOUT_SECONDS")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_TIMEOUT);
+
+        Self::new(database_connection, jobs, max_attempts, timeout)
+    }
+
+    /// Queues `event` for `article`; for a deletion, the article as it was
+    /// before being deleted.
+    pub fn article(&self, event: WebhookEvent, article: &entity::articles::Model) {
+        self.publish(event, ArticlePayload::from(article));
+    }
+
+    /// Queues `event` for `comment`; for a deletion, the comment as it was
+    /// before being deleted.
+    pub fn comment(&self, event: WebhookEvent, comment: &entity::comments::Model) {
+        self.publish(event, CommentPayload::from(comment));
+    }
+
+    fn publish(&self, event: WebhookEvent, data: impl Serialize) {
+        let payload = Payload {
+            event: event.as_str(),
+            occurred_at: Utc::now(),
+            data,
+        };
+        let payload = match serde_json::to_string(&payload) {
+            Ok(payload) => payload,
+            Err(err) => {
+                tracing::error!(error = %err, event = event.as_str(), "failed to serialize webhook payload");
+                return;
+            }
+        };
+
+        // Queued in the background, so that the request that caused the event
+        // neither waits for nor fails because of its webhooks.
+        let dispatcher = self.clone();
+        actix_web::rt::spawn(async move {
+            if let Err(err) = dispatcher.enqueue(event, &payload).await {
+                tracing::error!(error = %err, event = event.as_str(), "failed to queue webhook deliveries");
+            }
+        });
+    }
+
+    async fn enqueue(&self, event: WebhookEvent, payload: &str) -> Result<(), sea_orm::DbErr> {
+        let webhooks_repository =
+            repository::WebhooksRepository::new(self.database_connection.clone());
+        let webhook_deliveries_repository =
+            repository::WebhookDeliveriesRepository::new(self.database_connection.clone());
+
+        let webhook_ids = webhooks_repository
+            .find_active()
+            .await?
+            .into_iter()
+            .filter(|webhook| subscribes_to(&webhook.events, event))
+            .map(|webhook| webhook.id)
+            .collect::<Vec<i32>>();
+
+        if webhook_ids.is_empty() {
+            return Ok(());
+        }
+
+        webhook_deliveries_repository
+            .create_many(webhook_ids, event.as_str(), payload, self.max_attempts)
+            .await?;
+        self.jobs.wake();
+
+        Ok(())
+    }
+
+    /// Queues a copy of `delivery`, due immediately.
+    pub async fn redeliver(
+        &self,
+        delivery: &entity::webhook_deliveries::Model,
+    ) -> Result<entity::webhook_deliveries::Model, sea_orm::DbErr> {
+        let webhook_deliveries_repository =
+            repository::WebhookDeliveriesRepository::new(self.database_connection.clone());
+
+        let delivery = webhook_deliveries_repository
+            .redeliver(delivery, self.max_attempts)
+            .await?;
+        self.jobs.wake();
+
+        Ok(delivery)
+    }
+
+    /// Makes one attempt at delivery `delivery_id`, recording its outcome.
+    /// Fails when the attempt did, so that the job is retried; `last_attempt`
+    /// tells whether it will be.
+    pub async fn deliver(&self, delivery_id: i32, last_attempt: bool) -> anyhow::Result<()> {
+        let webhooks_repository =
+            repository::WebhooksRepository::new(self.database_connection.clone());
+        let webhook_deliveries_repository =
+            repository::WebhookDeliveriesRepository::new(self.database_connection.clone());
+
+        // Gone along with its webhook, or already settled.
+        let delivery = match webhook_deliveries_repository
+            .find_by_id(delivery_id)
+            .await?
+        {
+            Some(delivery) if delivery.status == WebhookDeliveryStatus::Pending => delivery,
+            _ => return Ok(()),
+        };
+        let webhook = match webhooks_repository.find_by_id(delivery.webhook_id).await? {
+            Some(webhook) => webhook,
+            None => return Ok(()),
+        };
+
+        let attempt = delivery.attempts + 1;
+        let started_at = Instant::now();
+
+        let (response_status, error) = if webhook.active {
+            let result = self
+                .client
+                .post(&webhook.url)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .header(EVENT_HEADER, &delivery.event)
+                .header(DELIVERY_HEADER, delivery.id)
+                .header(
+                    SIGNATURE_HEADER,
+                    signature(&webhook.secret, Utc::now().timestamp(), &delivery.payload),
+                )
+                .body(delivery.payload.clone())
+                .send()
+                .await;
+
+            match result {
+                Ok(response) => (Some(response.status().as_u16() as i32), None),
+                Err(err) => (None, Some(err.to_string())),
+            }
+        } else {
+            (None, Some("webhook is inactive".to_string()))
+        };
+        let duration_ms = started_at.elapsed().as_millis().min(i32::MAX as u128) as i32;
+        let succeeded = response_status.is_some_and(|status| (200..300).contains(&status));
+
+        // An inactive webhook is not retried; the delivery can be redelivered
+        // by hand once it is active again.
+        let (status, next_attempt_at) = if succeeded {
+            (WebhookDeliveryStatus::Succeeded, None)
+        } else if last_attempt || !webhook.active {
+            (WebhookDeliveryStatus::Failed, None)
+        } else {
+            (
+                WebhookDeliveryStatus::Pending,
+                Some(Utc::now() + jobs::backoff(attempt)),
+            )
+        };
+
+        let outcome = match (&error, response_status) {
+            (Some(error), _) => error.clone(),
+            (_, Some(response_status)) => format!("webhook responded with {response_status}"),
+            (None, None) => String::new(),
+        };
+
+        webhook_deliveries_repository
+            .record_attempt(
+                delivery,
+                entity::webhook_delivery_attempts::Model {
+                    id: 0,
+                    delivery_id,
+                    attempt,
+                    response_status,
+                    error,
+                    duration_ms,
+                    created_at: Utc::now(),
+                },
+                status,
+                next_attempt_at,
+            )
+            .await?;
+
+        if succeeded || !webhook.active {
+            return Ok(());
+        }
+
+        Err(anyhow::anyhow!(outcome))
+    }
+}